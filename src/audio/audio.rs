@@ -0,0 +1,500 @@
+use sdl2::{
+    mixer::{self, Channel, Chunk, Group, Sdl2MixerContext, DEFAULT_FORMAT},
+    AudioSubsystem, Sdl,
+};
+use serde::Deserialize;
+use std::{cell::RefCell, collections::HashMap, fs, path::Path, rc::Rc};
+
+use crate::event::event::{GameEvent, Target};
+use crate::light::light::ambient_light;
+use crate::settings::settings::Settings;
+use crate::world::world::World;
+
+pub const SOUNDS_PATH: &str = "assets/sounds/sounds.toml";
+pub const UI_CLICK: &str = "ui_click";
+const EFFECT_CHANNELS: i32 = 16; //Sound effects that can play at once
+const MUSIC_CHANNELS: [Channel; 2] = [Channel(0), Channel(1)]; //Reserved so tracks can crossfade
+const EFFECTS: Group = Group(1); //Every channel but the music ones
+const CROSSFADE_MS: i32 = 3000;
+const NIGHT_LIGHT: u8 = 5; //Sky light below which the night music plays
+
+// What a sound effect is, each has its own volume in the settings (under the master volume)
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Category {
+    Effects,
+    Ui,
+}
+
+// Plays sounds for the audio manager, so the game can run with a sound card or without one
+pub trait AudioBackend {
+    // Loads a sound file under a name
+    fn load(&mut self, name: &str, path: &Path) -> Result<(), String>;
+    // Plays a loaded sound once on a channel of the pool, volume is 0 - 1
+    fn play(&mut self, sound: &str, volume: f64);
+    // Fades a track in on the free music channel while the one playing fades out
+    fn crossfade(&mut self, track: &str, volume: f64, ms: i32);
+    // If a track is still playing, false once it ends so the next one can start
+    fn music_playing(&self) -> bool;
+    // Changes the volume of the track playing and of one still fading in or out
+    fn set_music_volume(&mut self, volume: f64);
+}
+
+// Mixer volumes are 0 - 128
+fn mix_volume(volume: f64) -> i32 {
+    (volume.clamp(0.0, 1.0) * mixer::MAX_VOLUME as f64).round() as i32
+}
+
+//Plays through SDL mixer, tracks are loaded as chunks like the sound effects since mixer music
+//can only play one at a time and can't crossfade
+pub struct MixerAudio {
+    _audio: AudioSubsystem,
+    _mixer: Option<Sdl2MixerContext>, //Ogg support, wav files play without it
+    sounds: HashMap<String, Chunk>,
+    music: usize,                //Music channel the current track is on
+    tracks: [Option<String>; 2], //Track on each music channel
+}
+
+impl MixerAudio {
+    pub fn open(sdl: &Sdl) -> Result<Self, String> {
+        let audio = sdl.audio()?;
+        mixer::open_audio(44_100, DEFAULT_FORMAT, 2, 1_024)?;
+        let context = mixer::init(mixer::InitFlag::OGG).ok();
+        let total = MUSIC_CHANNELS.len() as i32 + EFFECT_CHANNELS;
+        mixer::allocate_channels(total);
+        mixer::reserve_channels(MUSIC_CHANNELS.len() as i32);
+        EFFECTS.add_channels_range(MUSIC_CHANNELS.len() as i32, total - 1);
+        Ok(Self {
+            _audio: audio,
+            _mixer: context,
+            sounds: HashMap::new(),
+            music: 0,
+            tracks: [None, None],
+        })
+    }
+}
+
+impl Drop for MixerAudio {
+    fn drop(&mut self) {
+        Channel::all().halt();
+        // chunks have to be freed before the device closes
+        self.sounds.clear();
+        mixer::close_audio();
+    }
+}
+
+impl AudioBackend for MixerAudio {
+    fn load(&mut self, name: &str, path: &Path) -> Result<(), String> {
+        let chunk = Chunk::from_file(path)?;
+        self.sounds.insert(name.to_string(), chunk);
+        Ok(())
+    }
+
+    // With every channel busy the oldest sound is cut off for the new one
+    fn play(&mut self, sound: &str, volume: f64) {
+        let chunk = match self.sounds.get(sound) {
+            Some(c) => c,
+            None => return,
+        };
+        let channel = match EFFECTS.find_available().or_else(|| EFFECTS.find_oldest()) {
+            Some(c) => c,
+            None => return,
+        };
+        channel.set_volume(mix_volume(volume));
+        let _ = channel.play(chunk, 0);
+    }
+
+    // Fades change the channel volume as they go and set it back when they end, so the music
+    // volume is put on the track's chunk instead where changing it mid fade sticks
+    fn crossfade(&mut self, track: &str, volume: f64, ms: i32) {
+        MUSIC_CHANNELS[self.music].fade_out(ms);
+        let chunk = match self.sounds.get_mut(track) {
+            Some(c) => c,
+            None => return,
+        };
+        self.music = 1 - self.music;
+        let channel = MUSIC_CHANNELS[self.music];
+        channel.halt();
+        channel.set_volume(mixer::MAX_VOLUME);
+        chunk.set_volume(mix_volume(volume));
+        let _ = channel.fade_in(chunk, 0, ms);
+        self.tracks[self.music] = Some(track.to_string());
+    }
+
+    fn music_playing(&self) -> bool {
+        MUSIC_CHANNELS[self.music].is_playing()
+    }
+
+    fn set_music_volume(&mut self, volume: f64) {
+        for track in self.tracks.iter().flatten() {
+            if let Some(chunk) = self.sounds.get_mut(track) {
+                chunk.set_volume(mix_volume(volume));
+            }
+        }
+    }
+}
+
+//What the null backend was asked to do
+#[derive(Default)]
+pub struct AudioLog {
+    pub loaded: Vec<String>,
+    pub played: Vec<(String, f64)>, //Sound effects with their volume, oldest first
+    pub music: Vec<String>,         //Tracks started, oldest first
+    pub playing: bool,              //What music_playing says, set it to false to end a track
+    pub music_volume: f64,
+}
+
+//Plays nothing, only logs what it was asked to play. Used when there is no sound card, the log
+//is shared so whoever made the backend can still read it once the audio manager owns it
+pub struct NullAudio {
+    log: Rc<RefCell<AudioLog>>,
+}
+
+impl NullAudio {
+    pub fn new(log: Rc<RefCell<AudioLog>>) -> Self {
+        Self { log: log }
+    }
+}
+
+impl AudioBackend for NullAudio {
+    fn load(&mut self, name: &str, _path: &Path) -> Result<(), String> {
+        self.log.borrow_mut().loaded.push(name.to_string());
+        Ok(())
+    }
+
+    fn play(&mut self, sound: &str, volume: f64) {
+        self.log
+            .borrow_mut()
+            .played
+            .push((sound.to_string(), volume));
+    }
+
+    fn crossfade(&mut self, track: &str, volume: f64, _ms: i32) {
+        let mut log = self.log.borrow_mut();
+        log.music.push(track.to_string());
+        log.music_volume = volume;
+        log.playing = true;
+    }
+
+    fn music_playing(&self) -> bool {
+        self.log.borrow().playing
+    }
+
+    fn set_music_volume(&mut self, volume: f64) {
+        self.log.borrow_mut().music_volume = volume;
+    }
+}
+
+//Sounds to load, from assets/sounds/sounds.toml
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct SoundFile {
+    effects: HashMap<String, String>,        //Sound name to file
+    playlists: HashMap<String, Vec<String>>, //Playlist name to the files of its tracks
+}
+
+//Picks what plays: sound effects for the world's events and the gui, and music for where
+//the player is and the time of day
+pub struct Audio {
+    pub backend: Box<dyn AudioBackend>,
+    playlists: HashMap<String, Vec<String>>,
+    playlist: Option<String>, //Playlist playing
+    track: usize,             //Track of the playlist playing
+    volumes: (f64, f64, f64), //Music, effects and ui, with the master volume applied
+}
+
+// Music, effects and ui volumes with the master volume applied
+fn volumes(settings: &Settings) -> (f64, f64, f64) {
+    let m = settings.master_volume;
+    (
+        m * settings.music_volume,
+        m * settings.effects_volume,
+        m * settings.ui_volume,
+    )
+}
+
+impl Audio {
+    // Starts at the volumes in the settings, so sounds played before the first update are heard
+    pub fn new(mut backend: Box<dyn AudioBackend>, settings: &Settings) -> Self {
+        let volumes = volumes(settings);
+        backend.set_music_volume(volumes.0);
+        Self {
+            backend: backend,
+            playlists: HashMap::new(),
+            playlist: None,
+            track: 0,
+            volumes: volumes,
+        }
+    }
+
+    // Loads the sounds listed in the sound file, files are relative to it. Sounds that fail to
+    // load are left out and stay silent
+    pub fn load(&mut self, path: &Path) {
+        let text = match fs::read_to_string(path) {
+            Ok(t) => t,
+            Err(e) => {
+                println!("Could not read {}: {}", path.display(), e);
+                return;
+            }
+        };
+        let file: SoundFile = match toml::from_str(&text) {
+            Ok(f) => f,
+            Err(e) => {
+                println!("Could not parse {}: {}", path.display(), e);
+                return;
+            }
+        };
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let tracks = file.playlists.values().flatten();
+        for (name, file) in file.effects.iter().chain(tracks.map(|t| (t, t))) {
+            if let Err(e) = self.backend.load(name, &dir.join(file)) {
+                println!("Could not load sound {}: {}", file, e);
+            }
+        }
+        self.playlists = file.playlists;
+    }
+
+    // Plays a sound effect at the volume of its category
+    pub fn play(&mut self, sound: &str, category: Category) {
+        let volume = match category {
+            Category::Effects => self.volumes.1,
+            Category::Ui => self.volumes.2,
+        };
+        if volume > 0.0 {
+            self.backend.play(sound, volume);
+        }
+    }
+
+    // Runs once a tick before the world's events are cleared, without a world the menu music
+    // plays
+    pub fn update(&mut self, world: Option<&World>, settings: &Settings, tile_size: i32) {
+        let volumes = volumes(settings);
+        if volumes.0 != self.volumes.0 {
+            self.backend.set_music_volume(volumes.0);
+        }
+        self.volumes = volumes;
+
+        if let Some(world) = world {
+            for event in world.events.iter() {
+                if let Some(sound) = event_sound(world, event) {
+                    self.play(&sound, Category::Effects);
+                }
+            }
+        }
+        let playlist = match world {
+            Some(world) => self.pick_playlist(world, tile_size),
+            None => "menu".to_string(),
+        };
+        self.update_music(playlist);
+    }
+
+    // Night has its own music, during the day the biome under the player picks it (by the
+    // tile's name) and anywhere without music of its own plays the day playlist
+    fn pick_playlist(&self, world: &World, tile_size: i32) -> String {
+        if ambient_light(world.time) < NIGHT_LIGHT {
+            return "night".to_string();
+        }
+        let p = world.player.pos;
+        let biome = world.terrain(
+            (p.0 / tile_size as f64).floor() as i32,
+            (p.1 / tile_size as f64).floor() as i32,
+        );
+        match world.tiles.get(&biome) {
+            Some(tile) if self.playlists.contains_key(&tile.name.to_lowercase()) => {
+                tile.name.to_lowercase()
+            }
+            _ => "day".to_string(),
+        }
+    }
+
+    // Crossfades to a new playlist, or to the next track of this one when a track ends
+    fn update_music(&mut self, playlist: String) {
+        let tracks = match self.playlists.get(&playlist) {
+            Some(t) if !t.is_empty() => t,
+            _ => return,
+        };
+        if self.playlist.as_ref() == Some(&playlist) {
+            if self.backend.music_playing() {
+                return;
+            }
+            self.track = (self.track + 1) % tracks.len();
+        } else {
+            self.track = 0;
+        }
+        self.backend
+            .crossfade(&tracks[self.track], self.volumes.0, CROSSFADE_MS);
+        self.playlist = Some(playlist);
+    }
+}
+
+// Sound a world event makes, if any. Tile sounds are named after the tile's material
+fn event_sound(world: &World, event: &GameEvent) -> Option<String> {
+    let material = |id: &i32| world.tiles.get(id).and_then(|t| t.material.clone());
+    match event {
+        GameEvent::Footstep { id, .. } => material(id).map(|m| format!("step_{}", m)),
+        GameEvent::TileBroken { id, .. } => material(id).map(|m| format!("break_{}", m)),
+        GameEvent::TilePlaced { id, .. } => material(id).map(|m| format!("place_{}", m)),
+        GameEvent::Attack {
+            attacker: Target::Player,
+        } => Some("swing".to_string()),
+        GameEvent::Damaged {
+            target: Target::Player,
+            ..
+        } => Some("hurt".to_string()),
+        GameEvent::Damaged { .. } => Some("hit".to_string()),
+        GameEvent::Died { .. } => Some("die".to_string()),
+        GameEvent::PickedUp => Some("pickup".to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::player::Player;
+    use crate::world::world::DAY_LENGTH;
+
+    const TILE_SIZE: i32 = 50;
+
+    // Audio playing into a log the test keeps
+    fn audio() -> (Audio, Rc<RefCell<AudioLog>>) {
+        let log = Rc::new(RefCell::new(AudioLog::default()));
+        let mut audio = Audio::new(Box::new(NullAudio::new(log.clone())), &Settings::default());
+        audio.load(Path::new(SOUNDS_PATH));
+        (audio, log)
+    }
+
+    // Sound effects the events of one tick play
+    fn sounds(
+        audio: &mut Audio,
+        log: &RefCell<AudioLog>,
+        world: &mut World,
+        events: Vec<GameEvent>,
+    ) -> Vec<String> {
+        let before = log.borrow().played.len();
+        world.events = events;
+        audio.update(Some(world), &Settings::default(), TILE_SIZE);
+        world.events.clear();
+        let log = log.borrow();
+        let played = &log.played[before..];
+        // every sound the game asks for has to be in the sound file
+        for (sound, _) in played {
+            assert!(log.loaded.contains(sound), "{} is not loaded", sound);
+        }
+        played.iter().map(|(s, _)| s.clone()).collect()
+    }
+
+    // Track the last update started
+    fn track(log: &RefCell<AudioLog>) -> String {
+        log.borrow().music.last().unwrap().clone()
+    }
+
+    // Puts the player on the first tile along y = 0 with this terrain
+    fn stand_on(world: &mut World, terrain: i32) {
+        let x = (0..10_000)
+            .find(|x| world.terrain(*x, 0) == terrain)
+            .unwrap();
+        world.player.pos = ((x * TILE_SIZE) as f64 + 1.0, 1.0);
+    }
+
+    #[test]
+    fn footsteps_sound_like_the_material() {
+        let (mut audio, log) = audio();
+        let mut world = World::new(Player::new());
+        for (id, material) in [
+            (0, "grass"),
+            (1, "dirt"),
+            (2, "water"),
+            (3, "sand"),
+            (4, "snow"),
+            (6, "wood"),
+        ] {
+            let played = sounds(
+                &mut audio,
+                &log,
+                &mut world,
+                vec![GameEvent::Footstep { id: id }],
+            );
+            assert_eq!(played, vec![format!("step_{}", material)]);
+        }
+    }
+
+    #[test]
+    fn breaking_a_tree_chops_wood() {
+        let (mut audio, log) = audio();
+        let mut world = World::new(Player::new());
+        let played = sounds(
+            &mut audio,
+            &log,
+            &mut world,
+            vec![GameEvent::TileBroken { id: 5 }],
+        );
+        assert_eq!(played, vec!["break_wood"]);
+    }
+
+    #[test]
+    fn damage_sounds_depend_on_who_is_hit() {
+        let (mut audio, log) = audio();
+        let mut world = World::new(Player::new());
+        let player = GameEvent::Damaged {
+            target: Target::Player,
+        };
+        let mob = GameEvent::Damaged {
+            target: Target::Entity(0),
+        };
+        assert_eq!(
+            sounds(&mut audio, &log, &mut world, vec![player]),
+            vec!["hurt"]
+        );
+        assert_eq!(sounds(&mut audio, &log, &mut world, vec![mob]), vec!["hit"]);
+    }
+
+    #[test]
+    fn night_changes_the_playlist() {
+        let (mut audio, log) = audio();
+        audio.update(None, &Settings::default(), TILE_SIZE);
+        assert_eq!(track(&log), "music/menu.wav");
+
+        let mut world = World::new(Player::new());
+        stand_on(&mut world, 0);
+        world.time = DAY_LENGTH / 2;
+        sounds(&mut audio, &log, &mut world, Vec::new());
+        assert_eq!(track(&log), "music/day.wav");
+
+        world.time = 0;
+        sounds(&mut audio, &log, &mut world, Vec::new());
+        assert_eq!(track(&log), "music/night.wav");
+    }
+
+    #[test]
+    fn biomes_change_the_playlist() {
+        let (mut audio, log) = audio();
+        let mut world = World::new(Player::new());
+        world.time = DAY_LENGTH / 2;
+        stand_on(&mut world, 0);
+        sounds(&mut audio, &log, &mut world, Vec::new());
+        assert_eq!(track(&log), "music/day.wav");
+
+        stand_on(&mut world, 3);
+        sounds(&mut audio, &log, &mut world, Vec::new());
+        assert_eq!(track(&log), "music/sand.wav");
+
+        // the same playlist keeps its track playing instead of starting it over
+        sounds(&mut audio, &log, &mut world, Vec::new());
+        assert_eq!(log.borrow().music.len(), 2);
+    }
+
+    #[test]
+    fn volumes_apply_before_the_first_update() {
+        let settings = Settings {
+            master_volume: 0.5,
+            music_volume: 0.5,
+            ..Settings::default()
+        };
+        let log = Rc::new(RefCell::new(AudioLog::default()));
+        let mut audio = Audio::new(Box::new(NullAudio::new(log.clone())), &settings);
+        assert_eq!(log.borrow().music_volume, 0.25);
+
+        audio.play("ui_click", Category::Ui);
+        assert_eq!(log.borrow().played, vec![("ui_click".to_string(), 0.4)]);
+    }
+}
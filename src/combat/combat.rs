@@ -0,0 +1,218 @@
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::entity::entity::Entity;
+use crate::event::event::{GameEvent, Target};
+use crate::item::item::ItemStack;
+use crate::world::world::{collides, collides_with, Tile, World};
+
+const INVULNERABLE_TICKS: i32 = 15; //Ticks a target can't be hurt after being hit
+const KNOCKBACK_DECAY: f64 = 0.8; //Knockback velocity kept every tick
+
+const PLAYER_REACH: f64 = 45.0; //How far the player's attack reaches from its center
+const PLAYER_ARC: f64 = 0.5; //Cosine of half the attack arc (120 degrees total)
+const PLAYER_DAMAGE: i32 = 10;
+const PLAYER_KNOCKBACK: f64 = 8.0;
+const PLAYER_COOLDOWN: i32 = 12; //Ticks between player attacks
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DamageType {
+    Slash,
+    Pierce,
+    Bite,
+    Fire,
+    Poison,
+}
+
+impl DamageType {
+    // Physical damage is reduced by armor, everything else goes straight through
+    pub fn physical(&self) -> bool {
+        matches!(self, DamageType::Slash | DamageType::Pierce | DamageType::Bite)
+    }
+}
+
+//Health component, shared by the player and entities
+pub struct Health {
+    pub hp: i32,
+    pub max: i32,
+    pub armor: i32,            //Flat reduction of physical damage
+    pub invulnerable: i32,     //Ticks left until the target can be hurt again
+    pub knockback: (f64, f64), //Velocity added on top of normal movement
+}
+
+impl Health {
+    pub fn new(max: i32) -> Self {
+        Self {
+            hp: max,
+            max: max,
+            armor: 0,
+            invulnerable: 0,
+            knockback: (0.0, 0.0),
+        }
+    }
+}
+
+//Damage waiting to be applied at the end of the tick
+pub struct Hit {
+    pub target: Target,
+    pub amount: i32,
+    pub kind: DamageType,
+    pub knockback: (f64, f64),
+}
+
+// Gets the unit vector from a to b
+fn direction(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    let d = (b.0 - a.0, b.1 - a.1);
+    let dist = (d.0 * d.0 + d.1 * d.1).sqrt();
+    if dist == 0.0 {
+        return (0.0, 0.0);
+    }
+    (d.0 / dist, d.1 / dist)
+}
+
+// Swings the player's weapon if it wants to attack and the cooldown is over
+pub fn player_attack_system(world: &mut World, hits: &mut Vec<Hit>) {
+    if world.player.attack_cooldown > 0 {
+        world.player.attack_cooldown -= 1;
+    }
+    if !world.player.attacking || world.player.attack_cooldown > 0 {
+        return;
+    }
+    world.player.attack_cooldown = PLAYER_COOLDOWN;
+    world.events.push(GameEvent::Attack {
+        attacker: Target::Player,
+    });
+
+    // everything with health inside the arc in front of the player
+    let p = world.player.pos;
+    let facing = world.player.facing;
+    for (id, _) in world.entities.healths.iter() {
+        let pos = *world.entities.positions.get(id).unwrap();
+        let size = world.entities.hitboxes.get(id).unwrap();
+        let d = (pos.0 - p.0, pos.1 - p.1);
+        let dist = (d.0 * d.0 + d.1 * d.1).sqrt();
+        if dist > PLAYER_REACH + size.0.max(size.1) as f64 / 2.0 {
+            continue;
+        }
+        let dir = direction(p, pos);
+        if dist > 0.0 && dir.0 * facing.0 + dir.1 * facing.1 < PLAYER_ARC {
+            continue;
+        }
+        hits.push(Hit {
+            target: Target::Entity(*id),
+            amount: PLAYER_DAMAGE,
+            kind: DamageType::Slash,
+            knockback: (dir.0 * PLAYER_KNOCKBACK, dir.1 * PLAYER_KNOCKBACK),
+        });
+    }
+}
+
+// Turns attacks made by entities this tick into hits on the player
+pub fn entity_attack_system(world: &mut World, attackers: Vec<Entity>, hits: &mut Vec<Hit>) {
+    for id in attackers {
+        let ai = world.entities.ais.get(&id).unwrap();
+        let pos = *world.entities.positions.get(&id).unwrap();
+        let dir = direction(pos, world.player.pos);
+        world.events.push(GameEvent::Attack {
+            attacker: Target::Entity(id),
+        });
+        hits.push(Hit {
+            target: Target::Player,
+            amount: ai.damage,
+            kind: ai.damage_type,
+            knockback: (dir.0 * ai.knockback, dir.1 * ai.knockback),
+        });
+    }
+}
+
+// Applies hits, knocks targets back and kills (and loots) the ones that run out of health
+pub fn damage_system(world: &mut World, hits: Vec<Hit>) {
+    for hit in hits {
+        let pos;
+        let health = match hit.target {
+            Target::Player => {
+                pos = world.player.pos;
+                &mut world.player.health
+            }
+            Target::Entity(id) => {
+                pos = match world.entities.positions.get(&id) {
+                    Some(p) => *p,
+                    None => continue, // already died this tick
+                };
+                match world.entities.healths.get_mut(&id) {
+                    Some(h) => h,
+                    None => continue,
+                }
+            }
+        };
+        if health.invulnerable > 0 || health.hp <= 0 {
+            continue;
+        }
+
+        let mut amount = hit.amount;
+        if hit.kind.physical() {
+            amount = (amount - health.armor).max(1);
+        }
+        health.hp = (health.hp - amount).max(0);
+        health.invulnerable = INVULNERABLE_TICKS;
+        health.knockback = hit.knockback;
+        let dead = health.hp == 0;
+
+        world.events.push(GameEvent::Damaged { target: hit.target });
+        if !dead {
+            continue;
+        }
+
+        world.events.push(GameEvent::Died {
+            target: hit.target,
+            pos: pos,
+        });
+        if let Target::Entity(id) = hit.target {
+            let mut loot = Vec::new();
+            if let Some(kind) = world.entities.mobs.get(&id) {
+                for drop in world.mobs[*kind].drops.iter() {
+                    let count = world.rng.gen_range(drop.count.0..=drop.count.1);
+                    if count > 0 {
+                        loot.push(ItemStack {
+                            item: drop.item.clone(),
+                            count: count,
+                        });
+                    }
+                }
+            }
+            for stack in loot {
+                world.drop_item(pos, stack);
+            }
+            world.entities.despawn(id);
+        }
+    }
+}
+
+// Counts down invulnerability and slides knocked back targets (stopping at solid tiles)
+pub fn knockback_system(world: &mut World, tile_size: i32) {
+    let player = &mut world.player;
+    player.health.invulnerable = (player.health.invulnerable - 1).max(0);
+    let kb = player.health.knockback;
+    let old = player.pos;
+    player.pos = (old.0 + kb.0, old.1 + kb.1);
+    if collides(player.pos, player.size, &world.world, &world.tiles, tile_size) {
+        player.pos = old;
+    }
+    player.health.knockback = (kb.0 * KNOCKBACK_DECAY, kb.1 * KNOCKBACK_DECAY);
+
+    for (id, health) in world.entities.healths.iter_mut() {
+        health.invulnerable = (health.invulnerable - 1).max(0);
+        let kb = health.knockback;
+        let size = *world.entities.hitboxes.get(id).unwrap();
+        let aquatic = world.entities.aquatic.contains(id);
+        let blocked = |t: &Tile| if aquatic { !t.liquid } else { t.solid };
+        let pos = world.entities.positions.get_mut(id).unwrap();
+        let old = *pos;
+        *pos = (old.0 + kb.0, old.1 + kb.1);
+        if collides_with(*pos, size, &world.world, &world.tiles, tile_size, blocked) {
+            *pos = old;
+        }
+        health.knockback = (kb.0 * KNOCKBACK_DECAY, kb.1 * KNOCKBACK_DECAY);
+    }
+}
@@ -0,0 +1 @@
+pub mod combat;
\ No newline at end of file
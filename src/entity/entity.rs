@@ -0,0 +1,259 @@
+use rand::Rng;
+use sdl2::pixels::Color;
+use std::collections::{HashMap, HashSet};
+
+use crate::combat::combat::{DamageType, Health};
+use crate::item::item::DroppedItem;
+use crate::pathfinding::pathfinding::{footprint, PathCache, Query};
+use crate::world::world::{collides_with, Tile};
+
+// Entities are plain ids, their data lives in the component maps of Entities
+pub type Entity = u32;
+
+//Sprite component (how the entity is drawn)
+#[derive(Clone, Copy)]
+pub struct Sprite {
+    pub color: Color,
+    pub symbol: char, //Text symbol drawn on the entity
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum AiState {
+    Idle,
+    Wander,
+    Flee,   //Running away from the player
+    Chase,  //Running towards the player
+    Attack, //Next to the player and hitting it
+}
+
+//AI component, decides the velocity of the entity every tick
+pub struct Ai {
+    pub state: AiState,
+    pub speed: f64,     //Movement speed while walking
+    pub timer: i32,     //Ticks left in the current state (or until the next attack)
+    pub hostile: bool,  //Hostile entities chase the player, others flee from it
+    pub sight: f64,     //Distance at which the player is noticed
+    pub reach: f64,     //Distance at which the player can be attacked
+    pub damage: i32,    //Damage of one attack
+    pub damage_type: DamageType,
+    pub knockback: f64, //How hard an attack pushes the player
+    pub cooldown: i32,  //Ticks between attacks
+}
+
+impl Ai {
+    pub fn new(speed: f64) -> Self {
+        Self {
+            state: AiState::Idle,
+            speed: speed,
+            timer: 0,
+            hostile: false,
+            sight: 0.0,
+            reach: 0.0,
+            damage: 0,
+            damage_type: DamageType::Bite,
+            knockback: 0.0,
+            cooldown: 0,
+        }
+    }
+}
+
+//Holds every non-player actor in the world, one map per component
+pub struct Entities {
+    next_id: Entity,
+    pub alive: Vec<Entity>,
+    pub positions: HashMap<Entity, (f64, f64)>,
+    pub velocities: HashMap<Entity, (f64, f64)>,
+    pub hitboxes: HashMap<Entity, (u32, u32)>,
+    pub sprites: HashMap<Entity, Sprite>,
+    pub ais: HashMap<Entity, Ai>,
+    pub healths: HashMap<Entity, Health>,
+    pub items: HashMap<Entity, DroppedItem>, //Item entities lying on the ground
+    pub mobs: HashMap<Entity, usize>, //Index of the mob definition the entity was spawned from
+    pub aquatic: HashSet<Entity>,     //Entities that can only move through liquid tiles
+}
+
+impl Entities {
+    pub fn new() -> Self {
+        Self {
+            next_id: 0,
+            alive: Vec::new(),
+            positions: HashMap::new(),
+            velocities: HashMap::new(),
+            hitboxes: HashMap::new(),
+            sprites: HashMap::new(),
+            ais: HashMap::new(),
+            healths: HashMap::new(),
+            items: HashMap::new(),
+            mobs: HashMap::new(),
+            aquatic: HashSet::new(),
+        }
+    }
+
+    // Creates an entity with the components every actor needs, others can be inserted afterwards
+    pub fn spawn(&mut self, pos: (f64, f64), hitbox: (u32, u32), sprite: Sprite) -> Entity {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.alive.push(id);
+        self.positions.insert(id, pos);
+        self.velocities.insert(id, (0.0, 0.0));
+        self.hitboxes.insert(id, hitbox);
+        self.sprites.insert(id, sprite);
+        id
+    }
+
+    // Removes an entity and all of its components
+    pub fn despawn(&mut self, id: Entity) {
+        self.alive.retain(|e| *e != id);
+        self.positions.remove(&id);
+        self.velocities.remove(&id);
+        self.hitboxes.remove(&id);
+        self.sprites.remove(&id);
+        self.ais.remove(&id);
+        self.healths.remove(&id);
+        self.items.remove(&id);
+        self.mobs.remove(&id);
+        self.aquatic.remove(&id);
+    }
+}
+
+// Picks a velocity for every entity with an AI component, returns the entities that attacked the player
+pub fn ai_system(entities: &mut Entities, player: (f64, f64), rng: &mut impl Rng) -> Vec<Entity> {
+    let mut attackers = Vec::new();
+    for (id, ai) in entities.ais.iter_mut() {
+        let pos = match entities.positions.get(id) {
+            Some(p) => *p,
+            None => continue,
+        };
+        let vel = entities.velocities.entry(*id).or_insert((0.0, 0.0));
+
+        // direction and distance to the player
+        let d = (player.0 - pos.0, player.1 - pos.1);
+        let dist = (d.0 * d.0 + d.1 * d.1).sqrt();
+        let dir = if dist > 0.0 {
+            (d.0 / dist, d.1 / dist)
+        } else {
+            (0.0, 0.0)
+        };
+
+        ai.timer -= 1;
+        if dist < ai.sight {
+            if !ai.hostile {
+                ai.state = AiState::Flee;
+                *vel = (-dir.0 * ai.speed * 1.5, -dir.1 * ai.speed * 1.5);
+            } else if dist < ai.reach {
+                if ai.state != AiState::Attack {
+                    ai.state = AiState::Attack;
+                    ai.timer = ai.cooldown;
+                }
+                *vel = (0.0, 0.0);
+                if ai.timer <= 0 {
+                    attackers.push(*id);
+                    ai.timer = ai.cooldown;
+                }
+            } else {
+                ai.state = AiState::Chase;
+                *vel = (dir.0 * ai.speed, dir.1 * ai.speed);
+            }
+            continue;
+        }
+
+        // lost sight of the player, go back to idling
+        if ai.state == AiState::Flee || ai.state == AiState::Chase || ai.state == AiState::Attack {
+            ai.state = AiState::Wander;
+            ai.timer = 0;
+        }
+        if ai.timer > 0 {
+            continue;
+        }
+
+        if ai.state == AiState::Idle {
+            let angle: f64 = rng.gen_range(0.0..std::f64::consts::TAU);
+            ai.state = AiState::Wander;
+            *vel = (angle.cos() * ai.speed, angle.sin() * ai.speed);
+        } else {
+            ai.state = AiState::Idle;
+            *vel = (0.0, 0.0);
+        }
+        ai.timer = rng.gen_range(30..120);
+    }
+    attackers
+}
+
+// Steers chasing entities around obstacles using the path to the player
+pub fn path_system(
+    entities: &mut Entities,
+    world: &HashMap<(i32, i32), i32>,
+    tiles: &HashMap<i32, Tile>,
+    edits: u32,
+    cache: &mut PathCache,
+    player: (f64, f64),
+    tile_size: i32,
+) {
+    let to_tile = |p: (f64, f64)| {
+        (
+            (p.0 / tile_size as f64).floor() as i32,
+            (p.1 / tile_size as f64).floor() as i32,
+        )
+    };
+    let goal = to_tile(player);
+
+    for (id, ai) in entities.ais.iter() {
+        if ai.state != AiState::Chase {
+            continue;
+        }
+        let pos = *entities.positions.get(id).unwrap();
+        let mut query = Query::new(footprint(*entities.hitboxes.get(id).unwrap(), tile_size));
+        query.aquatic = entities.aquatic.contains(id);
+
+        // no path means the entity keeps running straight at the player
+        let path = match cache.find(world, tiles, edits, to_tile(pos), goal, &query) {
+            Some(p) => p,
+            None => continue,
+        };
+        let next = match path.first() {
+            Some(t) => *t,
+            None => continue,
+        };
+
+        // head for the center of the next tile
+        let target = (
+            (next.0 as f64 + 0.5) * tile_size as f64,
+            (next.1 as f64 + 0.5) * tile_size as f64,
+        );
+        let d = (target.0 - pos.0, target.1 - pos.1);
+        let dist = (d.0 * d.0 + d.1 * d.1).sqrt();
+        if dist > 0.0 {
+            entities
+                .velocities
+                .insert(*id, (d.0 / dist * ai.speed, d.1 / dist * ai.speed));
+        }
+    }
+}
+
+// Moves entities by their velocity, one axis at a time so they slide along solid tiles
+pub fn movement_system(
+    entities: &mut Entities,
+    world: &HashMap<(i32, i32), i32>,
+    tiles: &HashMap<i32, Tile>,
+    tile_size: i32,
+) {
+    for id in entities.alive.iter() {
+        let vel = match entities.velocities.get(id) {
+            Some(v) => *v,
+            None => continue,
+        };
+        let size = *entities.hitboxes.get(id).unwrap_or(&(0, 0));
+        let aquatic = entities.aquatic.contains(id);
+        let blocked = |t: &Tile| if aquatic { !t.liquid } else { t.solid };
+        let pos = entities.positions.get_mut(id).unwrap();
+
+        pos.0 += vel.0;
+        if collides_with(*pos, size, world, tiles, tile_size, blocked) {
+            pos.0 -= vel.0;
+        }
+        pos.1 += vel.1;
+        if collides_with(*pos, size, world, tiles, tile_size, blocked) {
+            pos.1 -= vel.1;
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use crate::entity::entity::Entity;
+
+// Something that can take part in combat
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Target {
+    Player,
+    Entity(Entity),
+}
+
+// Things that happened during a frame, read by the GUI and audio after World::update
+// (cleared at the end of every frame)
+#[derive(Clone, Debug)]
+pub enum GameEvent {
+    Attack {
+        attacker: Target,
+    },
+    Damaged {
+        target: Target,
+    },
+    Died {
+        target: Target,
+        pos: (f64, f64), //Where it died, in world units
+    },
+    Dropped {
+        pos: (f64, f64), //Where the item landed
+        item: String,
+        count: u32,
+    },
+    PickedUp,
+    TileBroken {
+        id: i32,
+    },
+    TilePlaced {
+        id: i32,
+    },
+    Footstep {
+        id: i32, //Tile stepped on
+    },
+}
+
+impl Target {
+    fn name(&self) -> String {
+        match self {
+            Target::Player => "Player".to_string(),
+            Target::Entity(id) => format!("Entity {}", id),
+        }
+    }
+}
+
+impl GameEvent {
+    // Line for the debug overlay's event log, None for footsteps which happen too often to list
+    pub fn describe(&self) -> Option<String> {
+        let text = match self {
+            GameEvent::Attack { attacker } => format!("{} attacked", attacker.name()),
+            GameEvent::Damaged { target } => format!("{} was hit", target.name()),
+            GameEvent::Died { target, pos } => {
+                format!("{} died at {:.0} {:.0}", target.name(), pos.0, pos.1)
+            }
+            GameEvent::Dropped { pos, item, count } => {
+                format!("Dropped {} {} at {:.0} {:.0}", count, item, pos.0, pos.1)
+            }
+            GameEvent::PickedUp => "Picked up an item".to_string(),
+            GameEvent::TileBroken { id } => format!("Broke tile {}", id),
+            GameEvent::TilePlaced { id } => format!("Placed tile {}", id),
+            GameEvent::Footstep { .. } => return None,
+        };
+        Some(text)
+    }
+}
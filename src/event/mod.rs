@@ -0,0 +1 @@
+pub mod event;
\ No newline at end of file
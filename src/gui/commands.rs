@@ -0,0 +1,240 @@
+use crate::item::item::ItemStack;
+use crate::pathfinding::pathfinding::{find_path, footprint};
+use crate::world::world::{World, DAY_LENGTH};
+
+const MAX_REGEN_RADIUS: i32 = 200; //Tiles, regenerating more at once freezes the game
+
+// Runs a command on the world with its arguments (without the name), returns what to print
+pub type CommandFn = fn(&mut World, &[&str], i32) -> Result<String, String>;
+
+pub struct Command {
+    pub name: &'static str,
+    pub usage: &'static str, //Arguments, shown by help and when they are wrong
+    pub help: &'static str,
+    pub run: CommandFn,
+}
+
+//Every command the console knows, by name
+pub struct Commands {
+    commands: Vec<Command>,
+}
+
+impl Commands {
+    // Registry with the built in commands
+    pub fn new() -> Self {
+        let mut commands = Self {
+            commands: Vec::new(),
+        };
+        commands.register(Command {
+            name: "tp",
+            usage: "x y",
+            help: "Teleports the player to a tile",
+            run: tp,
+        });
+        commands.register(Command {
+            name: "seed",
+            usage: "[seed]",
+            help: "Shows or changes the terrain seed (use regen to see it)",
+            run: seed,
+        });
+        commands.register(Command {
+            name: "give",
+            usage: "item [n]",
+            help: "Puts items in the inventory",
+            run: give,
+        });
+        commands.register(Command {
+            name: "settile",
+            usage: "x y id",
+            help: "Replaces a tile",
+            run: settile,
+        });
+        commands.register(Command {
+            name: "regen",
+            usage: "radius",
+            help: "Generates the tiles around the player again",
+            run: regen,
+        });
+        commands.register(Command {
+            name: "time",
+            usage: "[set ticks|morning|noon|evening|night]",
+            help: "Shows or changes the time of day",
+            run: time,
+        });
+        commands.register(Command {
+            name: "speed",
+            usage: "mult",
+            help: "Multiplies the player's speed",
+            run: speed,
+        });
+        commands.register(Command {
+            name: "path",
+            usage: "x y",
+            help: "Shows how many tiles the player would walk to reach a tile",
+            run: path,
+        });
+        commands.register(Command {
+            name: "noclip",
+            usage: "",
+            help: "Lets the player walk through solid tiles",
+            run: noclip,
+        });
+        commands
+    }
+
+    // Adds a command, replacing one with the same name
+    pub fn register(&mut self, command: Command) {
+        self.commands.retain(|c| c.name != command.name);
+        self.commands.push(command);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Command> {
+        self.commands.iter().find(|c| c.name == name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Command> {
+        self.commands.iter()
+    }
+
+    // Runs one line, the first word picks the command
+    pub fn run(&self, world: &mut World, line: &str, tile_size: i32) -> Result<String, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let name = match words.first() {
+            Some(name) => *name,
+            None => return Ok(String::new()),
+        };
+        let command = self
+            .get(name)
+            .ok_or_else(|| format!("Unknown command {}, try help", name))?;
+        (command.run)(world, &words[1..], tile_size)
+            .map_err(|e| format!("{} (usage: {} {})", e, command.name, command.usage))
+    }
+}
+
+fn arg<T: std::str::FromStr>(args: &[&str], i: usize) -> Result<T, String> {
+    let a = args.get(i).ok_or("Missing argument")?;
+    a.parse().map_err(|_| format!("Bad argument {}", a))
+}
+
+fn tp(world: &mut World, args: &[&str], tile_size: i32) -> Result<String, String> {
+    let x: i32 = arg(args, 0)?;
+    let y: i32 = arg(args, 1)?;
+    world.player.pos = (
+        (x as f64 + 0.5) * tile_size as f64,
+        (y as f64 + 0.5) * tile_size as f64,
+    );
+    Ok(format!("Teleported to {} {}", x, y))
+}
+
+fn seed(world: &mut World, args: &[&str], _tile_size: i32) -> Result<String, String> {
+    if args.is_empty() {
+        return Ok(format!("Seed: {}", world.seed));
+    }
+    world.set_seed(arg(args, 0)?);
+    Ok(format!("Seed set to {}", world.seed))
+}
+
+fn give(world: &mut World, args: &[&str], _tile_size: i32) -> Result<String, String> {
+    let name = args.first().ok_or("Missing argument")?;
+    let count: u32 = if args.len() > 1 { arg(args, 1)? } else { 1 };
+    // item names are matched ignoring case, so "give wood" works
+    let def = world
+        .items
+        .values()
+        .find(|d| d.name.eq_ignore_ascii_case(name))
+        .ok_or_else(|| format!("Unknown item {}", name))?;
+    let stack = ItemStack {
+        item: def.name.clone(),
+        count: count,
+    };
+    let left = world.player.inventory.add(&stack, def.stack);
+    Ok(format!("Gave {} {}", count - left, stack.item))
+}
+
+fn settile(world: &mut World, args: &[&str], _tile_size: i32) -> Result<String, String> {
+    let x: i32 = arg(args, 0)?;
+    let y: i32 = arg(args, 1)?;
+    let id: i32 = arg(args, 2)?;
+    let name = match world.tiles.get(&id) {
+        Some(tile) => tile.name.clone(),
+        None => return Err(format!("Unknown tile {}", id)),
+    };
+    world.set_tile((x, y), id);
+    Ok(format!("Set {} {} to {}", x, y, name))
+}
+
+fn regen(world: &mut World, args: &[&str], tile_size: i32) -> Result<String, String> {
+    let radius: i32 = arg(args, 0)?;
+    if !(0..=MAX_REGEN_RADIUS).contains(&radius) {
+        return Err(format!("Radius must be 0 - {}", MAX_REGEN_RADIUS));
+    }
+    let p = world.player.pos;
+    let center = (
+        (p.0 / tile_size as f64).floor() as i32,
+        (p.1 / tile_size as f64).floor() as i32,
+    );
+    for x in center.0 - radius..=center.0 + radius {
+        for y in center.1 - radius..=center.1 + radius {
+            world.generate_tile(x, y);
+        }
+    }
+    let side = radius * 2 + 1;
+    Ok(format!("Regenerated {} tiles", side * side))
+}
+
+fn time(world: &mut World, args: &[&str], _tile_size: i32) -> Result<String, String> {
+    let day = world.time / DAY_LENGTH;
+    match args.first() {
+        None => Ok(format!(
+            "Day {}, tick {} of {}",
+            day,
+            world.time % DAY_LENGTH,
+            DAY_LENGTH
+        )),
+        Some(&"set") => {
+            // times of day are fractions of the day, midnight is 0
+            let tick = match args.get(1) {
+                Some(&"morning") => DAY_LENGTH * 3 / 10,
+                Some(&"noon") => DAY_LENGTH / 2,
+                Some(&"evening") => DAY_LENGTH * 3 / 4,
+                Some(&"night") => 0,
+                _ => arg::<u32>(args, 1)? % DAY_LENGTH,
+            };
+            world.time = day * DAY_LENGTH + tick;
+            Ok(format!("Time set to tick {}", tick))
+        }
+        Some(a) => Err(format!("Bad argument {}", a)),
+    }
+}
+
+fn speed(world: &mut World, args: &[&str], _tile_size: i32) -> Result<String, String> {
+    let mult: f64 = arg(args, 0)?;
+    if mult <= 0.0 {
+        return Err("Speed must be above 0".to_string());
+    }
+    world.player.speed = mult;
+    Ok(format!("Speed set to {}", mult))
+}
+
+fn path(world: &mut World, args: &[&str], tile_size: i32) -> Result<String, String> {
+    let x: i32 = arg(args, 0)?;
+    let y: i32 = arg(args, 1)?;
+    let p = world.player.pos;
+    let from = (
+        (p.0 / tile_size as f64).floor() as i32,
+        (p.1 / tile_size as f64).floor() as i32,
+    );
+    let hitbox = footprint(world.player.size, tile_size);
+    match find_path(world, from, (x, y), hitbox) {
+        Some(path) => Ok(format!("Path to {} {}: {} tiles", x, y, path.len())),
+        None => Err(format!("No path to {} {}", x, y)),
+    }
+}
+
+fn noclip(world: &mut World, _args: &[&str], _tile_size: i32) -> Result<String, String> {
+    world.player.noclip = !world.player.noclip;
+    Ok(format!(
+        "Noclip {}",
+        if world.player.noclip { "on" } else { "off" }
+    ))
+}
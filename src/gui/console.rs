@@ -0,0 +1,210 @@
+use sdl2::{event::Event, keyboard::Keycode, pixels::Color, rect::Rect, ttf::Font};
+use std::{fs, io, path::Path};
+
+use crate::gui::commands::Commands;
+use crate::renderer::{backend::RenderBackend, renderer::Align};
+use crate::world::world::World;
+
+const SCROLLBACK: usize = 200; //Lines kept
+const HISTORY: usize = 50; //Commands kept for up and down
+const LINE_HEIGHT: i32 = 30;
+const INPUT_COLOR: Color = Color::RGB(255, 255, 255);
+const OUTPUT_COLOR: Color = Color::RGB(190, 190, 190);
+const ERROR_COLOR: Color = Color::RGB(240, 90, 90);
+
+//Developer console toggled with the backtick key, runs commands from the registry on the world
+pub struct Console {
+    pub open: bool,
+    pub commands: Commands,
+    lines: Vec<(String, Color)>, //Scrollback, oldest first
+    scroll: usize,               //Lines scrolled up from the newest
+    input: String,
+    history: Vec<String>,       //Entered commands, oldest first
+    history_pos: Option<usize>, //Entry shown while going through the history
+    pending: Vec<String>,       //Entered lines waiting for the world
+}
+
+impl Console {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            commands: Commands::new(),
+            lines: Vec::new(),
+            scroll: 0,
+            input: String::new(),
+            history: Vec::new(),
+            history_pos: None,
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    fn print(&mut self, text: &str, color: Color) {
+        for line in text.lines() {
+            self.lines.push((line.to_string(), color));
+        }
+        if self.lines.len() > SCROLLBACK {
+            let extra = self.lines.len() - SCROLLBACK;
+            self.lines.drain(..extra);
+        }
+        self.scroll = 0;
+    }
+
+    // Runs one line and prints the result, console only commands (help, clear, exec) are
+    // handled here and everything else goes to the registry
+    pub fn execute(&mut self, world: &mut World, line: &str, tile_size: i32) {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return;
+        }
+        self.print(&format!("> {}", line), INPUT_COLOR);
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("help") => {
+                let help: Vec<String> = self
+                    .commands
+                    .iter()
+                    .map(|c| format!("{} {} - {}", c.name, c.usage, c.help))
+                    .chain([
+                        "exec file - Runs every line of a file".to_string(),
+                        "clear - Empties the console".to_string(),
+                    ])
+                    .collect();
+                self.print(&help.join("\n"), OUTPUT_COLOR);
+            }
+            Some("clear") => self.lines.clear(),
+            Some("exec") => match words.next() {
+                Some(path) => {
+                    if let Err(e) = self.run_script(world, Path::new(path), tile_size) {
+                        self.print(&format!("Could not run {}: {}", path, e), ERROR_COLOR);
+                    }
+                }
+                None => self.print("Missing argument (usage: exec file)", ERROR_COLOR),
+            },
+            _ => match self.commands.run(world, line, tile_size) {
+                Ok(out) => self.print(&out, OUTPUT_COLOR),
+                Err(e) => self.print(&e, ERROR_COLOR),
+            },
+        }
+    }
+
+    // Runs every line of a file as a command, lines starting with # are comments
+    pub fn run_script(&mut self, world: &mut World, path: &Path, tile_size: i32) -> io::Result<()> {
+        let script = fs::read_to_string(path)?;
+        for line in script.lines() {
+            self.execute(world, line, tile_size);
+        }
+        Ok(())
+    }
+
+    // Runs the lines entered since the last call, the gui has no world to run them on itself
+    pub fn run_pending(&mut self, world: &mut World, tile_size: i32) {
+        for line in std::mem::take(&mut self.pending) {
+            self.execute(world, &line, tile_size);
+        }
+    }
+
+    // Takes keyboard input while open, returns true if the event was used
+    pub fn handle_event(&mut self, event: &Event) -> bool {
+        if !self.open {
+            return false;
+        }
+        match event {
+            // the default console key types a backtick too
+            Event::TextInput { text, .. } => {
+                self.input.extend(text.chars().filter(|c| *c != '`'));
+                true
+            }
+            Event::KeyDown { keycode, .. } => {
+                match keycode {
+                    Some(Keycode::Escape) => self.open = false,
+                    Some(Keycode::Backspace) => {
+                        self.input.pop();
+                    }
+                    Some(Keycode::Return) => {
+                        let line = std::mem::take(&mut self.input);
+                        if !line.trim().is_empty() {
+                            if self.history.last() != Some(&line) {
+                                self.history.push(line.clone());
+                            }
+                            if self.history.len() > HISTORY {
+                                self.history.remove(0);
+                            }
+                            self.pending.push(line);
+                        }
+                        self.history_pos = None;
+                    }
+                    Some(Keycode::Up) if !self.history.is_empty() => {
+                        let pos = match self.history_pos {
+                            Some(p) => p.saturating_sub(1),
+                            None => self.history.len() - 1,
+                        };
+                        self.history_pos = Some(pos);
+                        self.input = self.history[pos].clone();
+                    }
+                    Some(Keycode::Down) => {
+                        if let Some(p) = self.history_pos {
+                            if p + 1 < self.history.len() {
+                                self.history_pos = Some(p + 1);
+                                self.input = self.history[p + 1].clone();
+                            } else {
+                                self.history_pos = None;
+                                self.input.clear();
+                            }
+                        }
+                    }
+                    Some(Keycode::PageUp) => {
+                        self.scroll = (self.scroll + 5).min(self.lines.len().saturating_sub(1))
+                    }
+                    Some(Keycode::PageDown) => self.scroll = self.scroll.saturating_sub(5),
+                    _ => {}
+                }
+                true
+            }
+            Event::KeyUp { .. } => true,
+            Event::MouseWheel { y, .. } => {
+                let s =
+                    (self.scroll as i32 + y).clamp(0, self.lines.len().saturating_sub(1) as i32);
+                self.scroll = s as usize;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // Draws the scrollback and the input line over the top of the screen
+    pub fn draw(&self, canvas: &mut dyn RenderBackend, font: &Font) {
+        if !self.open {
+            return;
+        }
+        let (w, h) = canvas.size();
+        let panel = Rect::new(0, 0, w, h * 2 / 5);
+        canvas.fill_rect(panel, Color::RGBA(0, 0, 0, 200));
+
+        let input_y = panel.bottom() - LINE_HEIGHT - 4;
+        canvas.fill_rect(
+            Rect::new(0, input_y - 2, w, 1),
+            Color::RGBA(255, 255, 255, 80),
+        );
+        canvas.draw_text(
+            font,
+            INPUT_COLOR,
+            &format!("> {}_", self.input),
+            (8, input_y),
+            Align::LEFT,
+        );
+
+        // newest line right above the input, older ones going up until the panel is full
+        let mut y = input_y - LINE_HEIGHT - 4;
+        for (text, color) in self.lines.iter().rev().skip(self.scroll) {
+            if y < 0 {
+                break;
+            }
+            canvas.draw_text(font, *color, text, (8, y), Align::LEFT);
+            y -= LINE_HEIGHT;
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use sdl2::{pixels::Color, rect::Rect, ttf::Font};
+use std::{
+    collections::{HashSet, VecDeque},
+    time::Instant,
+};
+
+use crate::event::event::GameEvent;
+use crate::renderer::{backend::RenderBackend, camera::Camera, renderer::Align};
+use crate::world::world::{chunk_of, World};
+
+const SAMPLES: usize = 120; //Frames kept for the frame time graph
+const LOGGED_EVENTS: usize = 5; //World events listed, the oldest are dropped
+const LINE_HEIGHT: i32 = 30;
+const GRAPH_HEIGHT: u32 = 60; //Height of the frame time graph, 1/30s is halfway up
+
+//F3 overlay with frame times, coordinates and what is under the mouse, plus outline toggles
+//the renderer checks
+pub struct DebugOverlay {
+    pub open: bool,
+    pub chunk_borders: bool,    //Outline chunks
+    pub hitboxes: bool,         //Outline the player and entity collision boxes
+    pub solid_tiles: bool,      //Outline tiles that block movement
+    frame_times: VecDeque<f64>, //Seconds, oldest first
+    last_frame: Option<Instant>,
+    events: VecDeque<String>, //Descriptions of the latest world events, oldest first
+}
+
+impl DebugOverlay {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            chunk_borders: false,
+            hitboxes: false,
+            solid_tiles: false,
+            frame_times: VecDeque::new(),
+            last_frame: None,
+            events: VecDeque::new(),
+        }
+    }
+
+    // Records the time since the last frame, call once per frame
+    pub fn tick(&mut self) {
+        let now = Instant::now();
+        if let Some(last) = self.last_frame {
+            self.frame_times.push_back((now - last).as_secs_f64());
+            if self.frame_times.len() > SAMPLES {
+                self.frame_times.pop_front();
+            }
+        }
+        self.last_frame = Some(now);
+    }
+
+    // Adds a world event to the list, only while the overlay is open
+    pub fn log(&mut self, event: &GameEvent) {
+        if !self.open {
+            return;
+        }
+        if let Some(text) = event.describe() {
+            self.events.push_back(text);
+            if self.events.len() > LOGGED_EVENTS {
+                self.events.pop_front();
+            }
+        }
+    }
+
+    // Average frame time in seconds
+    fn frame_time(&self) -> f64 {
+        if self.frame_times.is_empty() {
+            return 0.0;
+        }
+        self.frame_times.iter().sum::<f64>() / self.frame_times.len() as f64
+    }
+
+    pub fn draw(
+        &self,
+        canvas: &mut dyn RenderBackend,
+        font: &Font,
+        world: &World,
+        camera: &Camera,
+        tile_size: i32,
+        m_coords: (i32, i32),
+    ) {
+        if !self.open {
+            return;
+        }
+        let p = world.player.pos;
+        let tile = (
+            (p.0 / tile_size as f64).floor() as i32,
+            (p.1 / tile_size as f64).floor() as i32,
+        );
+        let chunk = chunk_of(tile);
+        let frame = self.frame_time();
+        let fps = if frame > 0.0 { 1.0 / frame } else { 0.0 };
+
+        let hover = camera.screen_to_tile(m_coords, tile_size);
+        let hover_tile = world.world.get(&hover).and_then(|id| world.tiles.get(id));
+        let biome = world.tiles.get(&world.terrain(hover.0, hover.1)).unwrap();
+        let chunks: HashSet<(i32, i32)> = world.world.keys().map(|t| chunk_of(*t)).collect();
+
+        let mut lines = vec![
+            format!("{:.0} fps ({:.1} ms)", fps, frame * 1000.0),
+            format!("Pos: {:.1} {:.1}", p.0, p.1),
+            format!(
+                "Tile: {} {}  Chunk: {} {}",
+                tile.0, tile.1, chunk.0, chunk.1
+            ),
+        ];
+        match hover_tile {
+            Some(t) => lines.push(format!(
+                "Hover: {} {} {} ({})",
+                hover.0, hover.1, t.name, t.id
+            )),
+            None => lines.push(format!("Hover: {} {} not generated", hover.0, hover.1)),
+        }
+        lines.push(format!(
+            "Biome: {}  Elevation: {:.3}",
+            biome.name,
+            world.elevation(hover.0, hover.1)
+        ));
+        lines.push(format!(
+            "Chunks: {}  Entities: {}",
+            chunks.len(),
+            world.entities.alive.len()
+        ));
+        lines.extend(self.events.iter().cloned());
+        lines.push("G chunks  H hitboxes  K solid".to_string());
+
+        let panel = Rect::new(
+            0,
+            0,
+            560,
+            (lines.len() as i32 * LINE_HEIGHT + 20) as u32 + GRAPH_HEIGHT,
+        );
+        canvas.fill_rect(panel, Color::RGBA(0, 0, 0, 160));
+        for (i, line) in lines.iter().enumerate() {
+            canvas.draw_text(
+                font,
+                Color::WHITE,
+                line,
+                (8, 4 + i as i32 * LINE_HEIGHT),
+                Align::LEFT,
+            );
+        }
+
+        // one bar per frame, red when the game drops below 25 fps
+        let bottom = panel.bottom() - 8;
+        let target = GRAPH_HEIGHT as f64 / 2.0;
+        for (i, t) in self.frame_times.iter().enumerate() {
+            let h = (t * 30.0 * target).round().clamp(1.0, GRAPH_HEIGHT as f64) as u32;
+            let color = if *t > 1.0 / 25.0 {
+                Color::RGB(220, 60, 60)
+            } else {
+                Color::RGB(60, 220, 60)
+            };
+            canvas.fill_rect(Rect::new(8 + i as i32 * 3, bottom - h as i32, 2, h), color);
+        }
+        canvas.fill_rect(
+            Rect::new(8, bottom - target as i32, SAMPLES as u32 * 3, 1),
+            Color::WHITE,
+        );
+    }
+}
@@ -1,42 +1,523 @@
-use sdl2::{render::{Canvas, self}, video::Window, pixels::Color, ttf::Font};
+use sdl2::{
+    controller::Button, event::Event, keyboard::Keycode, mouse::MouseButton, rect::Rect, ttf::Font,
+};
 
-use crate::renderer::renderer::{Align, Renderer};
+use crate::gui::console::Console;
+use crate::gui::debug::DebugOverlay;
+use crate::gui::widget::{Layout, Widget, WidgetKind, ACCENT_COLOR, ROW_HEIGHT};
+use crate::renderer::{atlas::Atlas, backend::RenderBackend};
 
-pub struct Gui_Window{
-    pub pos: (i32, i32),
-    pub text: String,
-    pub align: Align,
+const SLIDER_STEPS: f64 = 20.0; //D-pad presses to move a slider from one end to the other
+
+// Something the player did with a widget, read by the game after the gui handled the input
+#[derive(Clone, PartialEq, Debug)]
+pub enum GuiEvent {
+    Clicked(String),           //Button id
+    Changed(String, f64),      //Slider id and new value
+    Selected(String, usize),   //Scroll list id and item index
+    Submitted(String, String), //Text input id and its text (on enter, which clears the input)
 }
 
-impl Gui_Window {
-    pub fn new(pos: (i32, i32), text: String, align: Align) -> Self {
+//Tree of widgets drawn over the world, routes mouse and keyboard input to them
+pub struct Gui {
+    pub root: Widget,          //Covers the whole screen
+    pub events: Vec<GuiEvent>, //Events from the current frame
+    pub debug: DebugOverlay,
+    pub console: Console,
+    focus: Option<String>,    //Text input that gets keyboard input
+    selected: Option<String>, //Widget picked with the d-pad, A uses it
+    mouse: (i32, i32),
+}
+
+impl Gui {
+    pub fn new() -> Self {
+        let mut root = Widget::panel("root", Layout::Free);
+        root.background = None;
+        root.padding = 0;
         Self {
-            pos: pos,
-            text: text,
-            align: align,
+            root: root,
+            events: Vec::new(),
+            debug: DebugOverlay::new(),
+            console: Console::new(),
+            focus: None,
+            selected: None,
+            mouse: (0, 0),
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Widget> {
+        self.root.find(id)
+    }
+
+    pub fn get_mut(&mut self, id: &str) -> Option<&mut Widget> {
+        self.root.find_mut(id)
+    }
+
+    // Adds a widget to the screen
+    pub fn add(&mut self, widget: Widget) {
+        self.root.children.push(widget);
+    }
+
+    // Changes the text of a label, button or text input
+    pub fn set_text(&mut self, id: &str, value: &str) {
+        if let Some(w) = self.get_mut(id) {
+            match &mut w.kind {
+                WidgetKind::Label { text, .. }
+                | WidgetKind::Button { text, .. }
+                | WidgetKind::TextInput { text, .. }
+                    // labels updated every frame usually keep their text, skip the allocation
+                    if text != value =>
+                {
+                    *text = value.to_string();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn set_value(&mut self, id: &str, v: f64) {
+        if let Some(w) = self.get_mut(id) {
+            match &mut w.kind {
+                WidgetKind::ProgressBar { value, .. } | WidgetKind::Slider { value, .. } => {
+                    *value = v
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn set_visible(&mut self, id: &str, visible: bool) {
+        if let Some(w) = self.get_mut(id) {
+            w.visible = visible;
+        }
+    }
+
+    // Lays out the tree for the screen size and draws it
+    pub fn draw(&mut self, canvas: &mut dyn RenderBackend, font: &Font, atlas: Option<&Atlas>) {
+        let (w, h) = canvas.size();
+        self.root.arrange(font, Rect::new(0, 0, w, h));
+        self.root.draw(canvas, font, atlas);
+        if let Some(w) = self.selected() {
+            canvas.draw_rect(w.rect, ACCENT_COLOR);
+        }
+        self.console.draw(canvas, font);
+    }
+
+    // Passes an input event to the widgets, returns true if the gui used it up so the game
+    // should ignore it
+    pub fn handle_event(&mut self, event: &Event) -> bool {
+        // the open console has the keyboard before any widget
+        if self.console.handle_event(event) {
+            return true;
+        }
+        match event {
+            Event::MouseMotion { x, y, .. } => {
+                self.mouse = (*x, *y);
+                let mut events = Vec::new();
+                let dragging = mouse_moved(&mut self.root, (*x, *y), &mut events);
+                self.events.extend(events);
+                dragging
+            }
+            Event::MouseButtonDown {
+                mouse_btn: MouseButton::Left,
+                x,
+                y,
+                ..
+            } => {
+                let id = match hit(&self.root, (*x, *y)) {
+                    Some(id) => id,
+                    None => {
+                        self.set_focus(None);
+                        return false;
+                    }
+                };
+                let w = self.root.find_mut(&id).unwrap();
+                let rect = w.rect;
+                let mut focus = None;
+                match &mut w.kind {
+                    WidgetKind::Button { pressed, .. } => *pressed = true,
+                    WidgetKind::Slider {
+                        value,
+                        min,
+                        max,
+                        dragging,
+                    } => {
+                        *dragging = true;
+                        *value = slider_value(rect, *x, *min, *max);
+                        self.events.push(GuiEvent::Changed(id.clone(), *value));
+                    }
+                    WidgetKind::ScrollList {
+                        items,
+                        scroll,
+                        selected,
+                    } => {
+                        let row = (*y - rect.y()) / ROW_HEIGHT + *scroll as i32;
+                        if row >= 0 && (row as usize) < items.len() {
+                            *selected = Some(row as usize);
+                            self.events
+                                .push(GuiEvent::Selected(id.clone(), row as usize));
+                        }
+                    }
+                    WidgetKind::TextInput { .. } => focus = Some(id.clone()),
+                    _ => {}
+                }
+                self.set_focus(focus);
+                true
+            }
+            Event::MouseButtonUp {
+                mouse_btn: MouseButton::Left,
+                x,
+                y,
+                ..
+            } => {
+                let mut events = Vec::new();
+                let used = released(&mut self.root, (*x, *y), &mut events);
+                self.events.extend(events);
+                used
+            }
+            Event::MouseWheel { y, .. } => {
+                let id = match hit(&self.root, self.mouse) {
+                    Some(id) => id,
+                    None => return false,
+                };
+                if let Some(WidgetKind::ScrollList { items, scroll, .. }) =
+                    self.root.find_mut(&id).map(|w| &mut w.kind)
+                {
+                    let s = (*scroll as i32 - y).clamp(0, items.len().saturating_sub(1) as i32);
+                    *scroll = s as usize;
+                }
+                true
+            }
+            Event::TextInput { text: typed, .. } => {
+                let id = match &self.focus {
+                    Some(id) => id.clone(),
+                    None => return false,
+                };
+                if let Some(WidgetKind::TextInput { text, .. }) =
+                    self.root.find_mut(&id).map(|w| &mut w.kind)
+                {
+                    text.push_str(typed);
+                }
+                true
+            }
+            Event::KeyDown { keycode, .. } => {
+                let id = match &self.focus {
+                    Some(id) => id.clone(),
+                    None => return false,
+                };
+                match keycode {
+                    Some(Keycode::Backspace) => {
+                        if let Some(WidgetKind::TextInput { text, .. }) =
+                            self.root.find_mut(&id).map(|w| &mut w.kind)
+                        {
+                            text.pop();
+                        }
+                    }
+                    Some(Keycode::Return) => {
+                        if let Some(WidgetKind::TextInput { text, .. }) =
+                            self.root.find_mut(&id).map(|w| &mut w.kind)
+                        {
+                            self.events
+                                .push(GuiEvent::Submitted(id.clone(), std::mem::take(text)));
+                        }
+                    }
+                    Some(Keycode::Escape) => self.set_focus(None),
+                    _ => {}
+                }
+                // every key goes to the focused input, so typing doesn't move the player
+                true
+            }
+            // keys released while typing belong to the text input as well
+            Event::KeyUp { .. } => self.focus.is_some(),
+            Event::ControllerButtonDown { button, .. } => self.navigate(*button),
+            _ => false,
+        }
+    }
+
+    // Moves the selection with the d-pad and uses the selected widget with A, returns false
+    // when there is nothing to select so the game gets the button
+    fn navigate(&mut self, button: Button) -> bool {
+        let mut ids = Vec::new();
+        navigable(&self.root, &mut ids);
+        if ids.is_empty() {
+            return false;
+        }
+        let i = match self
+            .selected
+            .as_ref()
+            .and_then(|s| ids.iter().position(|id| id == s))
+        {
+            Some(i) => i,
+            // the first press only picks a widget to start from
+            None => {
+                self.selected = match button {
+                    Button::DPadUp => ids.last().cloned(),
+                    Button::DPadDown | Button::DPadLeft | Button::DPadRight => ids.first().cloned(),
+                    _ => return false,
+                };
+                return true;
+            }
+        };
+        let id = ids[i].clone();
+        let w = self.root.find_mut(&id).unwrap();
+        let rows = (w.rect.height() as i32 / ROW_HEIGHT).max(1) as usize;
+        match (button, &mut w.kind) {
+            (Button::A, WidgetKind::Button { .. }) => self.events.push(GuiEvent::Clicked(id)),
+            (
+                Button::A,
+                WidgetKind::ScrollList {
+                    selected: Some(row),
+                    ..
+                },
+            ) => self.events.push(GuiEvent::Selected(id, *row)),
+            (
+                Button::DPadLeft | Button::DPadRight,
+                WidgetKind::Slider {
+                    value, min, max, ..
+                },
+            ) => {
+                let step = (*max - *min) / SLIDER_STEPS;
+                let step = if button == Button::DPadLeft {
+                    -step
+                } else {
+                    step
+                };
+                *value = (*value + step).clamp(*min, *max);
+                self.events.push(GuiEvent::Changed(id, *value));
+            }
+            // up and down go through the rows of a list before leaving it
+            (
+                Button::DPadUp | Button::DPadDown,
+                WidgetKind::ScrollList {
+                    items,
+                    scroll,
+                    selected,
+                },
+            ) if next_row(items.len(), *selected, button == Button::DPadUp).is_some() => {
+                let row = next_row(items.len(), *selected, button == Button::DPadUp).unwrap();
+                *selected = Some(row);
+                // keep the row on screen
+                if row < *scroll {
+                    *scroll = row;
+                } else if row >= *scroll + rows {
+                    *scroll = row + 1 - rows;
+                }
+            }
+            (Button::DPadUp, _) => {
+                self.selected = Some(ids[(i + ids.len() - 1) % ids.len()].clone())
+            }
+            (Button::DPadDown, _) => self.selected = Some(ids[(i + 1) % ids.len()].clone()),
+            _ => return false,
+        }
+        true
+    }
+
+    // Widget picked with the d-pad, if it is still on screen
+    fn selected(&self) -> Option<&Widget> {
+        let id = self.selected.as_ref()?;
+        let mut ids = Vec::new();
+        navigable(&self.root, &mut ids);
+        if !ids.contains(id) {
+            return None;
+        }
+        self.root.find(id)
+    }
+
+    fn set_focus(&mut self, id: Option<String>) {
+        if let Some(old) = self.focus.take() {
+            if let Some(WidgetKind::TextInput { focused, .. }) =
+                self.root.find_mut(&old).map(|w| &mut w.kind)
+            {
+                *focused = false;
+            }
+        }
+        if let Some(new) = id {
+            if let Some(WidgetKind::TextInput { focused, .. }) =
+                self.root.find_mut(&new).map(|w| &mut w.kind)
+            {
+                *focused = true;
+                self.focus = Some(new);
+            }
         }
     }
 
-    pub fn draw(&self, renderer: &Renderer, canvas: &mut Canvas<Window>, font: &Font) {
-        renderer.draw_text(canvas, font, Color::BLACK, self.text.clone(), self.pos, self.align);
+    // Last known mouse position
+    pub fn mouse(&self) -> (i32, i32) {
+        self.mouse
+    }
+
+    // If a text input or the console has the keyboard
+    pub fn typing(&self) -> bool {
+        self.focus.is_some() || self.console.open
     }
 }
 
-// 0: Mouse hover tile information
-pub struct Gui {
-    pub draw_stack: Vec<Gui_Window>,
+// Gets the id of the topmost interactive widget under a point
+fn hit(w: &Widget, p: (i32, i32)) -> Option<String> {
+    if !w.visible || !w.rect.contains_point(p) {
+        return None;
+    }
+    // children are drawn after their parent, so the last one is on top
+    for child in w.children.iter().rev() {
+        if let Some(id) = hit(child, p) {
+            return Some(id);
+        }
+    }
+    if w.interactive() {
+        return Some(w.id.clone());
+    }
+    None
 }
 
-impl Gui {
-    pub fn new() -> Self {
-        Self {
-            draw_stack: Vec::new(),
+// Gets the ids of the visible widgets the d-pad can select, in the order it goes through them
+fn navigable(w: &Widget, ids: &mut Vec<String>) {
+    if !w.visible {
+        return;
+    }
+    if matches!(
+        w.kind,
+        WidgetKind::Button { .. } | WidgetKind::Slider { .. } | WidgetKind::ScrollList { .. }
+    ) {
+        ids.push(w.id.clone());
+    }
+    for child in w.children.iter() {
+        navigable(child, ids);
+    }
+}
+
+// Row of a scroll list the d-pad moves to, None past either end so the selection leaves the list
+fn next_row(len: usize, row: Option<usize>, up: bool) -> Option<usize> {
+    match row {
+        None if len > 0 => Some(0),
+        Some(r) if up && r > 0 => Some(r - 1),
+        Some(r) if !up && r + 1 < len => Some(r + 1),
+        _ => None,
+    }
+}
+
+fn slider_value(rect: Rect, x: i32, min: f64, max: f64) -> f64 {
+    let t = ((x - rect.x()) as f64 / rect.width() as f64).clamp(0.0, 1.0);
+    min + t * (max - min)
+}
+
+// Updates hover states and dragged sliders, returns true if a slider is being dragged
+fn mouse_moved(w: &mut Widget, p: (i32, i32), events: &mut Vec<GuiEvent>) -> bool {
+    let rect = w.rect;
+    let mut dragged = false;
+    match &mut w.kind {
+        WidgetKind::Button { hovered, .. } => *hovered = w.visible && rect.contains_point(p),
+        WidgetKind::Slider {
+            value,
+            min,
+            max,
+            dragging: true,
+        } => {
+            *value = slider_value(rect, p.0, *min, *max);
+            events.push(GuiEvent::Changed(w.id.clone(), *value));
+            dragged = true;
         }
+        _ => {}
     }
+    for child in w.children.iter_mut() {
+        dragged |= mouse_moved(child, p, events);
+    }
+    dragged
+}
 
-    pub fn draw_windows(&self, renderer: &Renderer, canvas: &mut Canvas<Window>, font: &Font) {
-        for window in self.draw_stack.as_slice() {
-            window.draw(renderer, canvas, font);
+// Releases pressed buttons and dragged sliders, returns true if any was
+fn released(w: &mut Widget, p: (i32, i32), events: &mut Vec<GuiEvent>) -> bool {
+    let rect = w.rect;
+    let mut used = false;
+    match &mut w.kind {
+        WidgetKind::Button { pressed, .. } if *pressed => {
+            *pressed = false;
+            if rect.contains_point(p) {
+                events.push(GuiEvent::Clicked(w.id.clone()));
+            }
+            used = true;
+        }
+        WidgetKind::Slider { dragging, .. } if *dragging => {
+            *dragging = false;
+            used = true;
         }
+        _ => {}
+    }
+    for child in w.children.iter_mut() {
+        used |= released(child, p, events);
+    }
+    used
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(gui: &mut Gui, button: Button) -> bool {
+        gui.handle_event(&Event::ControllerButtonDown {
+            timestamp: 0,
+            which: 0,
+            button: button,
+        })
+    }
+
+    fn menu() -> Gui {
+        let mut gui = Gui::new();
+        gui.add(Widget::label("title", "Menu"));
+        gui.add(Widget::button("play", "Play"));
+        gui.add(Widget::slider("volume", 0.5, 0.0, 1.0));
+        gui.add(Widget::scroll_list(
+            "list",
+            vec!["a".to_string(), "b".to_string()],
+        ));
+        gui.add(Widget::button("quit", "Quit"));
+        gui
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn dpad_moves_through_the_widgets_and_a_clicks() {
+        let mut gui = menu();
+        assert!(press(&mut gui, Button::DPadDown));
+        assert!(press(&mut gui, Button::A));
+        assert_eq!(gui.events, vec![GuiEvent::Clicked("play".to_string())]);
+
+        // up from the first widget wraps around to the last
+        gui.events.clear();
+        press(&mut gui, Button::DPadUp);
+        press(&mut gui, Button::A);
+        assert_eq!(gui.events, vec![GuiEvent::Clicked("quit".to_string())]);
+    }
+
+    #[test]
+    fn dpad_moves_sliders_and_list_rows() {
+        let mut gui = menu();
+        press(&mut gui, Button::DPadDown);
+        press(&mut gui, Button::DPadDown);
+        press(&mut gui, Button::DPadRight);
+        assert_eq!(
+            gui.events,
+            vec![GuiEvent::Changed("volume".to_string(), 0.55)]
+        );
+
+        // the list takes up and down until its last row, then the selection moves on
+        gui.events.clear();
+        press(&mut gui, Button::DPadDown);
+        press(&mut gui, Button::DPadDown);
+        press(&mut gui, Button::DPadDown);
+        press(&mut gui, Button::A);
+        assert_eq!(gui.events, vec![GuiEvent::Selected("list".to_string(), 1)]);
+        press(&mut gui, Button::DPadDown);
+        press(&mut gui, Button::A);
+        assert_eq!(
+            gui.events.last(),
+            Some(&GuiEvent::Clicked("quit".to_string()))
+        );
+    }
+
+    #[test]
+    fn buttons_go_to_the_game_without_anything_to_select() {
+        let mut gui = Gui::new();
+        gui.add(Widget::label("title", "Hud"));
+        assert!(!press(&mut gui, Button::DPadDown));
+        assert!(!press(&mut gui, Button::A));
+    }
+}
@@ -0,0 +1,162 @@
+use sdl2::pixels::Color;
+
+use crate::gui::gui::Gui;
+use crate::gui::widget::{Anchor, Layout, Widget, WidgetKind};
+use crate::item::item::HOTBAR_SIZE;
+use crate::world::world::World;
+
+const MAX_STAT: f64 = 100.0; //Full stamina and hunger
+const LOW: f64 = 0.25; //Bars flash below this fraction
+const EASE: f64 = 0.15; //Fraction of the difference a bar moves towards its value every frame
+const FLASH_TICKS: u32 = 8; //Frames between flashes of a low bar
+const SLOT_SIZE: u32 = 56;
+const SLOT_COLOR: Color = Color::RGBA(20, 20, 30, 210);
+const SELECTED_COLOR: Color = Color::RGBA(90, 150, 255, 230);
+
+type Stat = fn(&World) -> f64;
+type Active = fn(&World) -> bool;
+
+// Bars of the HUD: widget id, color and how to read the value (0 - 1) from the world
+const BARS: [(&str, Color, Stat); 3] = [
+    ("hud_health", Color::RGB(210, 40, 40), |w| {
+        w.player.health.hp as f64 / w.player.health.max.max(1) as f64
+    }),
+    ("hud_stamina", Color::RGB(60, 200, 90), |w| {
+        w.player.stamina as f64 / MAX_STAT
+    }),
+    ("hud_hunger", Color::RGB(220, 150, 40), |w| {
+        w.player.hunger as f64 / MAX_STAT
+    }),
+];
+
+// Status effects shown as icons: widget id, symbol, color and when the player has it
+const EFFECTS: [(&str, &str, Color, Active); 3] = [
+    ("effect_hurt", "!", Color::RGB(200, 40, 40), |w| {
+        w.player.health.invulnerable > 0
+    }),
+    ("effect_tired", "z", Color::RGB(200, 200, 60), |w| {
+        (w.player.stamina as f64) < MAX_STAT * LOW
+    }),
+    ("effect_hungry", "%", Color::RGB(220, 150, 40), |w| {
+        (w.player.hunger as f64) < MAX_STAT * LOW
+    }),
+];
+
+//Health, stamina and hunger bars, the hotbar and status effect icons, kept in sync with the player
+pub struct Hud {
+    shown: [f64; 3], //Values the bars currently show, they ease towards the real ones
+    tick: u32,
+}
+
+impl Hud {
+    // Adds the HUD widgets to the gui
+    pub fn new(gui: &mut Gui) -> Self {
+        let mut bars =
+            Widget::panel("hud_bars", Layout::Vertical).at(Anchor::BottomLeft, (10, -10));
+        for (id, color, _) in BARS.iter() {
+            bars.children
+                .push(Widget::progress_bar(id, 1.0, *color).sized(200, 16));
+        }
+
+        let mut effects =
+            Widget::panel("hud_effects", Layout::Horizontal).at(Anchor::BottomLeft, (10, -100));
+        effects.background = None;
+        effects.padding = 0;
+        for (id, symbol, color, _) in EFFECTS.iter() {
+            let mut icon = Widget::panel(id, Layout::Free).sized(36, 36);
+            icon.background = Some(*color);
+            icon.padding = 0;
+            icon.visible = false;
+            icon.children
+                .push(Widget::label("", symbol).at(Anchor::Center, (0, 0)));
+            effects.children.push(icon);
+        }
+
+        let mut hotbar =
+            Widget::panel("hud_hotbar", Layout::Horizontal).at(Anchor::Bottom, (0, -10));
+        hotbar.padding = 4;
+        hotbar.spacing = 4;
+        for i in 0..HOTBAR_SIZE {
+            let mut slot =
+                Widget::panel(&format!("hud_slot_{}", i), Layout::Free).sized(SLOT_SIZE, SLOT_SIZE);
+            slot.background = Some(SLOT_COLOR);
+            slot.padding = 4;
+            let mut icon = Widget::image(&format!("hud_icon_{}", i), "", Color::WHITE)
+                .sized(SLOT_SIZE / 2, SLOT_SIZE / 2)
+                .at(Anchor::Center, (0, -4));
+            icon.visible = false;
+            slot.children.push(icon);
+            slot.children.push(
+                Widget::label(&format!("hud_count_{}", i), "").at(Anchor::BottomRight, (0, 6)),
+            );
+            hotbar.children.push(slot);
+        }
+
+        gui.add(bars);
+        gui.add(effects);
+        gui.add(hotbar);
+        Self {
+            shown: [1.0; 3],
+            tick: 0,
+        }
+    }
+
+    // Updates the widgets from the player, once per frame
+    pub fn update(&mut self, gui: &mut Gui, world: &World) {
+        self.tick += 1;
+        let flash = (self.tick / FLASH_TICKS).is_multiple_of(2);
+
+        for (i, (id, color, value)) in BARS.iter().enumerate() {
+            let target = value(world).clamp(0.0, 1.0);
+            self.shown[i] += (target - self.shown[i]) * EASE;
+            if (target - self.shown[i]).abs() < 0.001 {
+                self.shown[i] = target;
+            }
+            gui.set_value(id, self.shown[i]);
+            let c = if target < LOW && flash {
+                Color::WHITE
+            } else {
+                *color
+            };
+            if let Some(WidgetKind::ProgressBar { color, .. }) =
+                gui.get_mut(id).map(|w| &mut w.kind)
+            {
+                *color = c;
+            }
+        }
+
+        for (id, _, _, active) in EFFECTS.iter() {
+            gui.set_visible(id, active(world));
+        }
+
+        let inventory = &world.player.inventory;
+        for i in 0..HOTBAR_SIZE {
+            let stack = inventory.slots[i].as_ref();
+            let def = stack.and_then(|s| world.items.get(&s.item));
+            if let Some(slot) = gui.get_mut(&format!("hud_slot_{}", i)) {
+                slot.background = Some(if i == inventory.selected {
+                    SELECTED_COLOR
+                } else {
+                    SLOT_COLOR
+                });
+            }
+            let icon_id = format!("hud_icon_{}", i);
+            gui.set_visible(&icon_id, def.is_some());
+            if let Some(def) = def {
+                if let Some(WidgetKind::Image { frame, tint }) =
+                    gui.get_mut(&icon_id).map(|w| &mut w.kind)
+                {
+                    *tint = Color::RGB(def.color.0, def.color.1, def.color.2);
+                    if *frame != def.name {
+                        *frame = def.name.clone();
+                    }
+                }
+            }
+            let count = match stack {
+                Some(s) if s.count > 1 => s.count.to_string(),
+                _ => String::new(),
+            };
+            gui.set_text(&format!("hud_count_{}", i), &count);
+        }
+    }
+}
@@ -1 +1,6 @@
-pub mod gui;
\ No newline at end of file
+pub mod commands;
+pub mod console;
+pub mod debug;
+pub mod gui;
+pub mod hud;
+pub mod widget;
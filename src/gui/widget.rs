@@ -0,0 +1,442 @@
+use sdl2::{pixels::Color, rect::Rect, ttf::Font};
+
+use crate::renderer::{atlas::Atlas, backend::{RenderBackend, ATLAS_KEY}, renderer::Align};
+
+const TEXT_COLOR: Color = Color::RGB(235, 235, 235);
+const PANEL_COLOR: Color = Color::RGBA(20, 20, 30, 210);
+const BORDER_COLOR: Color = Color::RGB(90, 90, 110);
+const HOVER_COLOR: Color = Color::RGB(70, 70, 95);
+const PRESSED_COLOR: Color = Color::RGB(40, 40, 60);
+const FIELD_COLOR: Color = Color::RGB(10, 10, 15);
+pub const ACCENT_COLOR: Color = Color::RGB(90, 150, 255);
+pub const ROW_HEIGHT: i32 = 34; //Height of a scroll list row
+
+// Where a widget sits inside its parent when the parent doesn't stack its children
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Anchor {
+    TopLeft,
+    Center,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+// How a widget places its children
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    Free,       //Every child by its own anchor and offset
+    Vertical,   //Top to bottom, children fill the width
+    Horizontal, //Left to right, children fill the height
+}
+
+pub enum WidgetKind {
+    Panel,
+    Label {
+        text: String,
+        color: Color,
+    },
+    Button {
+        text: String,
+        hovered: bool,
+        pressed: bool,
+    },
+    ProgressBar {
+        value: f64, //0 - 1
+        color: Color,
+    },
+    Image {
+        frame: String, //Frame of the tile atlas
+        tint: Color,   //Drawn as a flat color without an atlas
+    },
+    ScrollList {
+        items: Vec<String>,
+        scroll: usize, //First item shown
+        selected: Option<usize>,
+    },
+    TextInput {
+        text: String,
+        placeholder: String,
+        focused: bool,
+    },
+    Slider {
+        value: f64,
+        min: f64,
+        max: f64,
+        dragging: bool,
+    },
+}
+
+//One element of the gui tree, found by its id
+pub struct Widget {
+    pub id: String,
+    pub kind: WidgetKind,
+    pub anchor: Anchor,
+    pub offset: (i32, i32),
+    pub size: (u32, u32), //0 picks the size from the content (or fills the parent)
+    pub padding: i32,     //Space between the edge and the children
+    pub spacing: i32,     //Space between stacked children
+    pub layout: Layout,
+    pub background: Option<Color>,
+    pub visible: bool,
+    pub children: Vec<Widget>,
+    pub rect: Rect, //Where the widget ended up on screen in the last layout
+}
+
+impl Widget {
+    pub fn new(id: &str, kind: WidgetKind) -> Self {
+        Self {
+            id: id.to_string(),
+            kind: kind,
+            anchor: Anchor::TopLeft,
+            offset: (0, 0),
+            size: (0, 0),
+            padding: 0,
+            spacing: 0,
+            layout: Layout::Free,
+            background: None,
+            visible: true,
+            children: Vec::new(),
+            rect: Rect::new(0, 0, 1, 1),
+        }
+    }
+
+    pub fn panel(id: &str, layout: Layout) -> Self {
+        let mut w = Self::new(id, WidgetKind::Panel);
+        w.layout = layout;
+        w.background = Some(PANEL_COLOR);
+        w.padding = 8;
+        w.spacing = 6;
+        w
+    }
+
+    pub fn label(id: &str, text: &str) -> Self {
+        Self::new(
+            id,
+            WidgetKind::Label {
+                text: text.to_string(),
+                color: TEXT_COLOR,
+            },
+        )
+    }
+
+    pub fn button(id: &str, text: &str) -> Self {
+        let mut w = Self::new(
+            id,
+            WidgetKind::Button {
+                text: text.to_string(),
+                hovered: false,
+                pressed: false,
+            },
+        );
+        w.padding = 6;
+        w
+    }
+
+    pub fn progress_bar(id: &str, value: f64, color: Color) -> Self {
+        Self::new(
+            id,
+            WidgetKind::ProgressBar {
+                value: value,
+                color: color,
+            },
+        )
+    }
+
+    pub fn image(id: &str, frame: &str, tint: Color) -> Self {
+        Self::new(
+            id,
+            WidgetKind::Image {
+                frame: frame.to_string(),
+                tint: tint,
+            },
+        )
+    }
+
+    pub fn scroll_list(id: &str, items: Vec<String>) -> Self {
+        Self::new(
+            id,
+            WidgetKind::ScrollList {
+                items: items,
+                scroll: 0,
+                selected: None,
+            },
+        )
+    }
+
+    pub fn text_input(id: &str, placeholder: &str) -> Self {
+        let mut w = Self::new(
+            id,
+            WidgetKind::TextInput {
+                text: String::new(),
+                placeholder: placeholder.to_string(),
+                focused: false,
+            },
+        );
+        w.padding = 4;
+        w
+    }
+
+    pub fn slider(id: &str, value: f64, min: f64, max: f64) -> Self {
+        Self::new(
+            id,
+            WidgetKind::Slider {
+                value: value,
+                min: min,
+                max: max,
+                dragging: false,
+            },
+        )
+    }
+
+    // Builder style setters, so trees can be written as one expression
+    pub fn at(mut self, anchor: Anchor, offset: (i32, i32)) -> Self {
+        self.anchor = anchor;
+        self.offset = offset;
+        self
+    }
+
+    pub fn sized(mut self, w: u32, h: u32) -> Self {
+        self.size = (w, h);
+        self
+    }
+
+    // Finds a widget in this tree by id
+    pub fn find(&self, id: &str) -> Option<&Widget> {
+        if self.id == id {
+            return Some(self);
+        }
+        self.children.iter().find_map(|c| c.find(id))
+    }
+
+    pub fn find_mut(&mut self, id: &str) -> Option<&mut Widget> {
+        if self.id == id {
+            return Some(self);
+        }
+        self.children.iter_mut().find_map(|c| c.find_mut(id))
+    }
+
+    // If the widget reacts to the mouse, other widgets let clicks through to the world
+    pub fn interactive(&self) -> bool {
+        match self.kind {
+            WidgetKind::Label { .. } | WidgetKind::Image { .. } => false,
+            WidgetKind::Panel => self.background.is_some(),
+            _ => true,
+        }
+    }
+
+    // Size the widget wants, 0 where it takes whatever its parent gives it
+    pub fn measure(&self, font: &Font) -> (u32, u32) {
+        let pad = self.padding.max(0) as u32 * 2;
+        let content = match &self.kind {
+            WidgetKind::Label { text, .. } | WidgetKind::Button { text, .. } => {
+                let (w, h) = font.size_of(text).unwrap_or((0, 0));
+                (w + pad, h.max(font.height() as u32) + pad)
+            }
+            WidgetKind::TextInput { .. } => (0, font.height() as u32 + pad),
+            _ => self.measure_children(font),
+        };
+        (
+            if self.size.0 > 0 {
+                self.size.0
+            } else {
+                content.0
+            },
+            if self.size.1 > 0 {
+                self.size.1
+            } else {
+                content.1
+            },
+        )
+    }
+
+    // Size taken up by stacked children, 0 for free layouts
+    fn measure_children(&self, font: &Font) -> (u32, u32) {
+        let visible: Vec<(u32, u32)> = self
+            .children
+            .iter()
+            .filter(|c| c.visible)
+            .map(|c| c.measure(font))
+            .collect();
+        if visible.is_empty() || self.layout == Layout::Free {
+            return (0, 0);
+        }
+        let pad = self.padding.max(0) as u32 * 2;
+        let gaps = self.spacing.max(0) as u32 * (visible.len() as u32 - 1);
+        let widest = visible.iter().map(|s| s.0).max().unwrap();
+        let tallest = visible.iter().map(|s| s.1).max().unwrap();
+        match self.layout {
+            Layout::Vertical => (
+                widest + pad,
+                visible.iter().map(|s| s.1).sum::<u32>() + gaps + pad,
+            ),
+            _ => (
+                visible.iter().map(|s| s.0).sum::<u32>() + gaps + pad,
+                tallest + pad,
+            ),
+        }
+    }
+
+    // Places the widget at rect and lays out its children inside it
+    pub fn arrange(&mut self, font: &Font, rect: Rect) {
+        self.rect = rect;
+        let p = self.padding;
+        let content = Rect::new(
+            rect.x() + p,
+            rect.y() + p,
+            (rect.width() as i32 - p * 2).max(1) as u32,
+            (rect.height() as i32 - p * 2).max(1) as u32,
+        );
+
+        let mut cursor = (content.x(), content.y());
+        let layout = self.layout;
+        let spacing = self.spacing;
+        for child in self.children.iter_mut().filter(|c| c.visible) {
+            let size = child.measure(font);
+            let child_rect = match layout {
+                Layout::Free => {
+                    let w = if size.0 > 0 { size.0 } else { content.width() };
+                    let h = if size.1 > 0 { size.1 } else { content.height() };
+                    let r = anchored(content, w, h, child.anchor);
+                    Rect::new(r.x() + child.offset.0, r.y() + child.offset.1, w, h)
+                }
+                Layout::Vertical => {
+                    let h = size.1.max(1);
+                    let r = Rect::new(cursor.0, cursor.1, content.width(), h);
+                    cursor.1 += h as i32 + spacing;
+                    r
+                }
+                Layout::Horizontal => {
+                    let w = size.0.max(1);
+                    let r = Rect::new(cursor.0, cursor.1, w, content.height());
+                    cursor.0 += w as i32 + spacing;
+                    r
+                }
+            };
+            child.arrange(font, child_rect);
+        }
+    }
+
+    // Draws the widget and then its children on top
+    pub fn draw(&self, canvas: &mut dyn RenderBackend, font: &Font, atlas: Option<&Atlas>) {
+        if !self.visible {
+            return;
+        }
+        let r = self.rect;
+        if let Some(bg) = self.background {
+            canvas.fill_rect(r, bg);
+            if matches!(self.kind, WidgetKind::Panel) {
+                canvas.draw_rect(r, BORDER_COLOR);
+            }
+        }
+
+        match &self.kind {
+            WidgetKind::Panel => {}
+            WidgetKind::Label { text, color } => {
+                let pos = (r.x() + self.padding, r.y() + self.padding);
+                canvas.draw_text(font, *color, text, pos, Align::LEFT);
+            }
+            WidgetKind::Button {
+                text,
+                hovered,
+                pressed,
+            } => {
+                let bg = if *pressed {
+                    PRESSED_COLOR
+                } else if *hovered {
+                    HOVER_COLOR
+                } else {
+                    PANEL_COLOR
+                };
+                canvas.fill_rect(r, bg);
+                canvas.draw_rect(r, BORDER_COLOR);
+                let c = r.center();
+                canvas.draw_text(font, TEXT_COLOR, text, (c.x(), c.y()), Align::CENTER);
+            }
+            WidgetKind::ProgressBar { value, color } => {
+                canvas.fill_rect(r, FIELD_COLOR);
+                let w = (r.width() as f64 * value.clamp(0.0, 1.0)).round() as u32;
+                if w > 0 {
+                    canvas.fill_rect(Rect::new(r.x(), r.y(), w, r.height()), *color);
+                }
+                canvas.draw_rect(r, BORDER_COLOR);
+            }
+            WidgetKind::Image { frame, tint } => {
+                match atlas.and_then(|a| a.frame(frame).map(|src| (a, src))) {
+                    Some((a, src)) => canvas.blit_sprite(ATLAS_KEY, &a.sheet, src, r),
+                    None => canvas.fill_rect(r, *tint),
+                }
+            }
+            WidgetKind::ScrollList {
+                items,
+                scroll,
+                selected,
+            } => {
+                canvas.fill_rect(r, FIELD_COLOR);
+                let row = ROW_HEIGHT;
+                let rows = (r.height() as i32 / row).max(1) as usize;
+                for (i, item) in items.iter().enumerate().skip(*scroll).take(rows) {
+                    let y = r.y() + (i - scroll) as i32 * row;
+                    if Some(i) == *selected {
+                        canvas.fill_rect(Rect::new(r.x(), y, r.width(), row as u32), HOVER_COLOR);
+                    }
+                    canvas.draw_text(font, TEXT_COLOR, item, (r.x() + 4, y), Align::LEFT);
+                }
+                // scroll bar
+                if items.len() > rows {
+                    let h = (r.height() as usize * rows / items.len()).max(4) as u32;
+                    let y = r.y() + (r.height() as usize * scroll / items.len()) as i32;
+                    canvas.fill_rect(Rect::new(r.right() - 4, y, 4, h), ACCENT_COLOR);
+                }
+                canvas.draw_rect(r, BORDER_COLOR);
+            }
+            WidgetKind::TextInput {
+                text,
+                placeholder,
+                focused,
+            } => {
+                canvas.fill_rect(r, FIELD_COLOR);
+                canvas.draw_rect(r, if *focused { ACCENT_COLOR } else { BORDER_COLOR });
+                let pos = (r.x() + self.padding, r.y() + self.padding);
+                if text.is_empty() && !focused {
+                    canvas.draw_text(font, BORDER_COLOR, placeholder, pos, Align::LEFT);
+                } else if *focused {
+                    canvas.draw_text(font, TEXT_COLOR, &format!("{}_", text), pos, Align::LEFT);
+                } else {
+                    canvas.draw_text(font, TEXT_COLOR, text, pos, Align::LEFT);
+                }
+            }
+            WidgetKind::Slider {
+                value, min, max, ..
+            } => {
+                let c = r.center();
+                canvas.fill_rect(Rect::new(r.x(), c.y() - 2, r.width(), 4), BORDER_COLOR);
+                let t = ((value - min) / (max - min)).clamp(0.0, 1.0);
+                let x = r.x() + (t * r.width() as f64) as i32;
+                canvas.fill_rect(
+                    Rect::from_center((x, c.y()), 10, r.height().min(20)),
+                    ACCENT_COLOR,
+                );
+            }
+        }
+
+        for child in self.children.iter() {
+            child.draw(canvas, font, atlas);
+        }
+    }
+}
+
+// Gets the rect of a w*h box placed inside area by an anchor
+fn anchored(area: Rect, w: u32, h: u32, anchor: Anchor) -> Rect {
+    let (free_w, free_h) = (
+        area.width() as i32 - w as i32,
+        area.height() as i32 - h as i32,
+    );
+    let (fx, fy) = match anchor {
+        Anchor::TopLeft => (0, 0),
+        Anchor::Center => (1, 1),
+        Anchor::BottomLeft => (0, 2),
+        Anchor::Bottom => (1, 2),
+        Anchor::BottomRight => (2, 2),
+    };
+    Rect::new(area.x() + free_w * fx / 2, area.y() + free_h * fy / 2, w, h)
+}
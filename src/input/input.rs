@@ -0,0 +1,463 @@
+use sdl2::{
+    controller::{Axis, Button, GameController},
+    event::Event,
+    keyboard::Keycode,
+    mouse::MouseButton,
+    GameControllerSubsystem,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::Path,
+};
+
+pub const BINDINGS_PATH: &str = "config/bindings.toml";
+const DEADZONE: f64 = 0.25; //Stick tilt (0 - 1) ignored so worn sticks don't drift
+
+// Something the player can do, the game reads these instead of keys
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Sprint,
+    Attack,
+    Break,    //Break the tile under the cursor
+    Interact, //Use the selected item on the tile under the cursor
+    OpenInventory,
+    Map,
+    Pause,
+    Console,
+    Hotbar(usize), //Select a hotbar slot (0 - 8)
+    HotbarPrev,
+    HotbarNext,
+}
+
+// Every action with its name in the bindings file, in the order the controls menu shows them
+pub const ACTIONS: [(Action, &str); 23] = [
+    (Action::MoveUp, "move_up"),
+    (Action::MoveDown, "move_down"),
+    (Action::MoveLeft, "move_left"),
+    (Action::MoveRight, "move_right"),
+    (Action::Sprint, "sprint"),
+    (Action::Attack, "attack"),
+    (Action::Break, "break"),
+    (Action::Interact, "interact"),
+    (Action::OpenInventory, "open_inventory"),
+    (Action::Map, "map"),
+    (Action::Pause, "pause"),
+    (Action::Console, "console"),
+    (Action::Hotbar(0), "hotbar_1"),
+    (Action::Hotbar(1), "hotbar_2"),
+    (Action::Hotbar(2), "hotbar_3"),
+    (Action::Hotbar(3), "hotbar_4"),
+    (Action::Hotbar(4), "hotbar_5"),
+    (Action::Hotbar(5), "hotbar_6"),
+    (Action::Hotbar(6), "hotbar_7"),
+    (Action::Hotbar(7), "hotbar_8"),
+    (Action::Hotbar(8), "hotbar_9"),
+    (Action::HotbarPrev, "hotbar_prev"),
+    (Action::HotbarNext, "hotbar_next"),
+];
+
+// A key, mouse button or controller button an action can be bound to
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Input {
+    Key(Keycode),
+    Mouse(MouseButton),
+    Pad(Button),
+}
+
+const MOUSE_BUTTONS: [(MouseButton, &str); 5] = [
+    (MouseButton::Left, "Mouse Left"),
+    (MouseButton::Middle, "Mouse Middle"),
+    (MouseButton::Right, "Mouse Right"),
+    (MouseButton::X1, "Mouse X1"),
+    (MouseButton::X2, "Mouse X2"),
+];
+
+impl Input {
+    // Name used in the bindings file and the controls menu, keys and controller buttons use
+    // SDL's names
+    pub fn name(&self) -> String {
+        match self {
+            Input::Key(key) => key.name(),
+            Input::Pad(button) => format!("Pad {}", button.string()),
+            Input::Mouse(button) => MOUSE_BUTTONS
+                .iter()
+                .find(|(b, _)| b == button)
+                .map_or("Mouse ?", |(_, n)| n)
+                .to_string(),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        if let Some((button, _)) = MOUSE_BUTTONS.iter().find(|(_, n)| *n == name) {
+            return Some(Input::Mouse(*button));
+        }
+        if let Some(button) = name.strip_prefix("Pad ") {
+            return Button::from_string(button).map(Input::Pad);
+        }
+        Keycode::from_name(name).map(Input::Key)
+    }
+
+    // Controller buttons are bound separately from keys and the mouse, so one action can have both
+    fn is_pad(&self) -> bool {
+        matches!(self, Input::Pad(_))
+    }
+}
+
+//Which inputs trigger each action, an action can have any number of them
+pub struct Bindings {
+    map: HashMap<Action, Vec<Input>>,
+}
+
+impl Bindings {
+    pub fn new() -> Self {
+        use Input::{Key, Mouse, Pad};
+        let mut map = HashMap::new();
+        map.insert(
+            Action::MoveUp,
+            vec![Key(Keycode::W), Key(Keycode::Up), Pad(Button::DPadUp)],
+        );
+        map.insert(
+            Action::MoveDown,
+            vec![Key(Keycode::S), Key(Keycode::Down), Pad(Button::DPadDown)],
+        );
+        map.insert(
+            Action::MoveLeft,
+            vec![Key(Keycode::A), Key(Keycode::Left), Pad(Button::DPadLeft)],
+        );
+        map.insert(
+            Action::MoveRight,
+            vec![Key(Keycode::D), Key(Keycode::Right), Pad(Button::DPadRight)],
+        );
+        map.insert(
+            Action::Sprint,
+            vec![Key(Keycode::LShift), Pad(Button::LeftStick)],
+        );
+        map.insert(Action::Attack, vec![Key(Keycode::Space), Pad(Button::A)]);
+        map.insert(
+            Action::Break,
+            vec![Mouse(MouseButton::Left), Pad(Button::X)],
+        );
+        map.insert(
+            Action::Interact,
+            vec![Mouse(MouseButton::Right), Pad(Button::B)],
+        );
+        map.insert(Action::OpenInventory, vec![Key(Keycode::E), Pad(Button::Y)]);
+        map.insert(Action::Map, vec![Key(Keycode::M), Pad(Button::Back)]);
+        map.insert(
+            Action::Pause,
+            vec![Key(Keycode::Escape), Pad(Button::Start)],
+        );
+        map.insert(Action::Console, vec![Key(Keycode::Backquote)]);
+        map.insert(Action::HotbarPrev, vec![Pad(Button::LeftShoulder)]);
+        map.insert(Action::HotbarNext, vec![Pad(Button::RightShoulder)]);
+        let numbers = [
+            Keycode::Num1,
+            Keycode::Num2,
+            Keycode::Num3,
+            Keycode::Num4,
+            Keycode::Num5,
+            Keycode::Num6,
+            Keycode::Num7,
+            Keycode::Num8,
+            Keycode::Num9,
+        ];
+        for (i, key) in numbers.iter().enumerate() {
+            map.insert(Action::Hotbar(i), vec![Key(*key)]);
+        }
+        Self { map: map }
+    }
+
+    // Loads the bindings file on top of the defaults, actions missing from it keep theirs.
+    // A missing file just means nothing was rebound yet
+    pub fn load(path: &Path) -> Self {
+        let mut bindings = Self::new();
+        let text = match fs::read_to_string(path) {
+            Ok(t) => t,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return bindings,
+            Err(e) => {
+                println!("Could not read {}: {}", path.display(), e);
+                return bindings;
+            }
+        };
+        let file: HashMap<String, Vec<String>> = match toml::from_str(&text) {
+            Ok(f) => f,
+            Err(e) => {
+                println!("Could not parse {}: {}", path.display(), e);
+                return bindings;
+            }
+        };
+        for (name, inputs) in file {
+            let action = match ACTIONS.iter().find(|(_, n)| *n == name) {
+                Some((a, _)) => *a,
+                None => {
+                    println!("Unknown action {} in {}", name, path.display());
+                    continue;
+                }
+            };
+            let mut parsed = Vec::new();
+            for input in inputs {
+                match Input::from_name(&input) {
+                    Some(i) => parsed.push(i),
+                    None => println!("Unknown input {} in {}", input, path.display()),
+                }
+            }
+            bindings.map.insert(action, parsed);
+        }
+        bindings
+    }
+
+    // Writes every binding, one line per action
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut text = String::new();
+        for (action, name) in ACTIONS.iter() {
+            let inputs: Vec<String> = self
+                .get(*action)
+                .iter()
+                .map(|i| toml::Value::String(i.name()).to_string())
+                .collect();
+            text.push_str(&format!("{} = [{}]\n", name, inputs.join(", ")));
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, text)
+    }
+
+    pub fn get(&self, action: Action) -> &[Input] {
+        self.map.get(&action).map_or(&[], |v| v.as_slice())
+    }
+
+    // Makes input the only one of its kind (keyboard and mouse, or controller) for the action,
+    // taking it away from any other action
+    pub fn bind(&mut self, action: Action, input: Input) {
+        for inputs in self.map.values_mut() {
+            inputs.retain(|i| *i != input);
+        }
+        let inputs = self.map.entry(action).or_default();
+        inputs.retain(|i| i.is_pad() != input.is_pad());
+        inputs.push(input);
+    }
+
+    // Actions an input triggers
+    pub fn actions(&self, input: Input) -> impl Iterator<Item = Action> + '_ {
+        self.map
+            .iter()
+            .filter(move |(_, inputs)| inputs.contains(&input))
+            .map(|(action, _)| *action)
+    }
+}
+
+// Gets the input an event presses, key repeats don't count
+pub fn pressed_input(event: &Event) -> Option<Input> {
+    match event {
+        Event::KeyDown {
+            keycode: Some(key),
+            repeat: false,
+            ..
+        } => Some(Input::Key(*key)),
+        Event::MouseButtonDown { mouse_btn, .. } => Some(Input::Mouse(*mouse_btn)),
+        Event::ControllerButtonDown { button, .. } => Some(Input::Pad(*button)),
+        _ => None,
+    }
+}
+
+// Stick position (-1 - 1 on both axes) from raw axis values, tilts inside the deadzone are 0
+// and the rest is stretched so the stick still reaches full speed
+fn stick(x: i16, y: i16) -> (f64, f64) {
+    let v = (x as f64 / i16::MAX as f64, y as f64 / i16::MAX as f64);
+    let len = v.0.hypot(v.1);
+    if len < DEADZONE {
+        return (0.0, 0.0);
+    }
+    let scale = ((len - DEADZONE) / (1.0 - DEADZONE)).min(1.0) / len;
+    (v.0 * scale, v.1 * scale)
+}
+
+//Inputs held down right now and the bindings that turn them into actions, plus the
+//controllers plugged in and their sticks
+pub struct InputState {
+    pub bindings: Bindings,
+    pub using_pad: bool, //If a controller was used last (aiming goes by the stick, not the mouse)
+    held: HashSet<Input>, //Keys and mouse buttons
+    pads: Option<GameControllerSubsystem>, //None without a window
+    controllers: HashMap<u32, GameController>, //By joystick instance id
+    pad_held: HashSet<(u32, Button)>, //Controller buttons by the instance id they are held on
+    axes: HashMap<(u32, Axis), i16>, //Last value of every stick axis of every controller
+}
+
+impl InputState {
+    pub fn new(bindings: Bindings, pads: Option<GameControllerSubsystem>) -> Self {
+        Self {
+            bindings: bindings,
+            using_pad: false,
+            held: HashSet::new(),
+            pads: pads,
+            controllers: HashMap::new(),
+            pad_held: HashSet::new(),
+            axes: HashMap::new(),
+        }
+    }
+
+    // Tracks held keys and buttons and plugged in controllers, call for every event whatever
+    // state gets it
+    pub fn handle_event(&mut self, event: &Event) {
+        match event {
+            Event::KeyDown {
+                keycode: Some(key), ..
+            } => {
+                self.held.insert(Input::Key(*key));
+                self.using_pad = false;
+            }
+            Event::KeyUp {
+                keycode: Some(key), ..
+            } => {
+                self.held.remove(&Input::Key(*key));
+            }
+            Event::MouseButtonDown { mouse_btn, .. } => {
+                self.held.insert(Input::Mouse(*mouse_btn));
+                self.using_pad = false;
+            }
+            Event::MouseButtonUp { mouse_btn, .. } => {
+                self.held.remove(&Input::Mouse(*mouse_btn));
+            }
+            Event::MouseMotion { .. } => self.using_pad = false,
+            // SDL sends this for controllers plugged in at startup as well
+            Event::ControllerDeviceAdded { which, .. } => {
+                if let Some(pads) = &self.pads {
+                    match pads.open(*which) {
+                        Ok(c) => {
+                            println!("Controller connected: {}", c.name());
+                            self.controllers.insert(c.instance_id(), c);
+                        }
+                        Err(e) => println!("Could not open controller: {}", e),
+                    }
+                }
+            }
+            Event::ControllerDeviceRemoved { which, .. } => {
+                if let Some(c) = self.controllers.remove(which) {
+                    println!("Controller disconnected: {}", c.name());
+                }
+                // nothing stays held on a controller that is gone, the others keep theirs
+                self.pad_held.retain(|(pad, _)| pad != which);
+                self.axes.retain(|(pad, _), _| pad != which);
+            }
+            Event::ControllerButtonDown { which, button, .. } => {
+                self.pad_held.insert((*which, *button));
+                self.using_pad = true;
+            }
+            Event::ControllerButtonUp { which, button, .. } => {
+                self.pad_held.remove(&(*which, *button));
+            }
+            Event::ControllerAxisMotion {
+                which, axis, value, ..
+            } => {
+                self.axes.insert((*which, *axis), *value);
+                if stick(*value, 0) != (0.0, 0.0) {
+                    self.using_pad = true;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn axis(&self, pad: u32, axis: Axis) -> i16 {
+        self.axes.get(&(pad, axis)).copied().unwrap_or(0)
+    }
+
+    // Stick of whichever controller tilts it furthest, so two controllers don't add up
+    fn pad_stick(&self, x: Axis, y: Axis) -> (f64, f64) {
+        let pads: HashSet<u32> = self.axes.keys().map(|(pad, _)| *pad).collect();
+        pads.iter()
+            .map(|pad| stick(self.axis(*pad, x), self.axis(*pad, y)))
+            .fold((0.0, 0.0), |best, s| {
+                if s.0.hypot(s.1) > best.0.hypot(best.1) {
+                    s
+                } else {
+                    best
+                }
+            })
+    }
+
+    // Left stick, moves the player
+    pub fn move_stick(&self) -> (f64, f64) {
+        self.pad_stick(Axis::LeftX, Axis::LeftY)
+    }
+
+    // Right stick, moves the target cursor
+    pub fn aim_stick(&self) -> (f64, f64) {
+        self.pad_stick(Axis::RightX, Axis::RightY)
+    }
+
+    // Actions with at least one of their inputs held down, on any controller
+    pub fn held(&self) -> HashSet<Action> {
+        let pad = self.pad_held.iter().map(|(_, b)| Input::Pad(*b));
+        self.held
+            .iter()
+            .copied()
+            .chain(pad)
+            .flat_map(|i| self.bindings.actions(i))
+            .collect()
+    }
+
+    // Actions an event just started
+    pub fn pressed(&self, event: &Event) -> Vec<Action> {
+        match pressed_input(event) {
+            Some(input) => self.bindings.actions(input).collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn button(which: u32, button: Button) -> Event {
+        Event::ControllerButtonDown {
+            timestamp: 0,
+            which: which,
+            button: button,
+        }
+    }
+
+    fn axis(which: u32, axis: Axis, value: i16) -> Event {
+        Event::ControllerAxisMotion {
+            timestamp: 0,
+            which: which,
+            axis: axis,
+            value: value,
+        }
+    }
+
+    #[test]
+    fn unplugging_a_controller_keeps_the_others_held() {
+        let mut input = InputState::new(Bindings::new(), None);
+        input.handle_event(&button(1, Button::DPadUp));
+        input.handle_event(&button(2, Button::DPadLeft));
+        input.handle_event(&axis(1, Axis::LeftX, i16::MAX));
+        input.handle_event(&axis(2, Axis::RightY, i16::MAX));
+        input.handle_event(&Event::ControllerDeviceRemoved {
+            timestamp: 0,
+            which: 1,
+        });
+
+        let held = input.held();
+        assert!(!held.contains(&Action::MoveUp));
+        assert!(held.contains(&Action::MoveLeft));
+        assert_eq!(input.move_stick(), (0.0, 0.0));
+        assert_eq!(input.aim_stick(), (0.0, 1.0));
+    }
+
+    #[test]
+    fn stick_goes_by_the_controller_tilted_furthest() {
+        let mut input = InputState::new(Bindings::new(), None);
+        input.handle_event(&axis(1, Axis::LeftX, i16::MAX));
+        input.handle_event(&axis(2, Axis::LeftY, i16::MAX / 2));
+        assert_eq!(input.move_stick(), (1.0, 0.0));
+    }
+}
@@ -0,0 +1,205 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fs, path::Path};
+
+use crate::entity::entity::Entity;
+use crate::event::event::GameEvent;
+use crate::world::world::World;
+
+pub const INVENTORY_SIZE: usize = 36; //Slots in the player's inventory
+pub const HOTBAR_SIZE: usize = 9; //First slots of the inventory, selectable with 1-9
+
+fn default_stack() -> u32 {
+    64
+}
+
+//One kind of item, loaded from assets/data/items.toml
+#[derive(Deserialize)]
+pub struct ItemDef {
+    pub name: String,
+    pub color: (u8, u8, u8),
+    pub symbol: char,
+    #[serde(default = "default_stack")]
+    pub stack: u32, //Max amount in one inventory slot
+    pub places: Option<i32>, //Tile put down when the item is used
+}
+
+#[derive(Deserialize)]
+struct ItemFile {
+    item: Vec<ItemDef>,
+}
+
+// Loads item definitions keyed by name, a missing or broken file means items have no extra data
+pub fn load_items(path: &Path) -> HashMap<String, ItemDef> {
+    let mut items = HashMap::new();
+    let text = match fs::read_to_string(path) {
+        Ok(t) => t,
+        Err(e) => {
+            println!("Could not read {}: {}", path.display(), e);
+            return items;
+        }
+    };
+    match toml::from_str::<ItemFile>(&text) {
+        Ok(f) => {
+            for def in f.item {
+                items.insert(def.name.clone(), def);
+            }
+        }
+        Err(e) => println!("Could not parse {}: {}", path.display(), e),
+    }
+    items
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct ItemStack {
+    pub item: String,
+    pub count: u32,
+}
+
+pub struct Inventory {
+    pub slots: Vec<Option<ItemStack>>,
+    pub selected: usize, //Selected hotbar slot
+}
+
+impl Inventory {
+    pub fn new() -> Self {
+        Self {
+            slots: vec![None; INVENTORY_SIZE],
+            selected: 0,
+        }
+    }
+
+    // Adds as much of the stack as fits (filling existing stacks first), returns the amount left over
+    pub fn add(&mut self, stack: &ItemStack, max_stack: u32) -> u32 {
+        let mut left = stack.count;
+        for slot in self.slots.iter_mut().flatten() {
+            if left == 0 {
+                break;
+            }
+            if slot.item != stack.item || slot.count >= max_stack {
+                continue;
+            }
+            let n = left.min(max_stack - slot.count);
+            slot.count += n;
+            left -= n;
+        }
+        for slot in self.slots.iter_mut() {
+            if left == 0 {
+                break;
+            }
+            if slot.is_some() {
+                continue;
+            }
+            let n = left.min(max_stack);
+            *slot = Some(ItemStack {
+                item: stack.item.clone(),
+                count: n,
+            });
+            left -= n;
+        }
+        left
+    }
+
+    // Removes one item from the selected slot and returns its name
+    pub fn take_selected(&mut self) -> Option<String> {
+        let slot = &mut self.slots[self.selected];
+        let stack = slot.as_mut()?;
+        let item = stack.item.clone();
+        stack.count -= 1;
+        if stack.count == 0 {
+            *slot = None;
+        }
+        Some(item)
+    }
+}
+
+pub const ITEM_LIFETIME: u32 = 30 * 60 * 5; //Ticks before a dropped item disappears (5 minutes)
+const MERGE_RADIUS: f64 = 20.0; //Dropped stacks of the same item closer than this merge
+const MAGNET_RADIUS: f64 = 80.0; //Dropped items closer than this fly towards the player
+const PICKUP_RADIUS: f64 = 15.0; //Dropped items closer than this go into the inventory
+const MAGNET_SPEED: f64 = 4.0;
+
+//Component of item entities lying in the world
+pub struct DroppedItem {
+    pub stack: ItemStack,
+    pub age: u32, //Ticks since the item was dropped
+}
+
+// Ages, merges and picks up dropped items
+pub fn item_system(world: &mut World) {
+    let mut gone: Vec<Entity> = Vec::new();
+
+    // despawn old items
+    for (id, dropped) in world.entities.items.iter_mut() {
+        dropped.age += 1;
+        if dropped.age > ITEM_LIFETIME {
+            gone.push(*id);
+        }
+    }
+
+    // merge nearby stacks of the same item into the oldest one
+    let mut ids: Vec<Entity> = world.entities.items.keys().copied().collect();
+    ids.sort();
+    for i in 0..ids.len() {
+        if gone.contains(&ids[i]) {
+            continue;
+        }
+        for j in (i + 1)..ids.len() {
+            if gone.contains(&ids[j]) {
+                continue;
+            }
+            let a = *world.entities.positions.get(&ids[i]).unwrap();
+            let b = *world.entities.positions.get(&ids[j]).unwrap();
+            if (a.0 - b.0).hypot(a.1 - b.1) > MERGE_RADIUS {
+                continue;
+            }
+            let other = world.entities.items.get(&ids[j]).unwrap();
+            let (stack, age) = (other.stack.clone(), other.age);
+            let dropped = world.entities.items.get_mut(&ids[i]).unwrap();
+            if dropped.stack.item != stack.item {
+                continue;
+            }
+            dropped.stack.count += stack.count;
+            dropped.age = dropped.age.min(age);
+            gone.push(ids[j]);
+        }
+    }
+
+    // pull items towards the player and pick them up
+    let p = world.player.pos;
+    for (id, dropped) in world.entities.items.iter_mut() {
+        if gone.contains(id) {
+            continue;
+        }
+        let pos = *world.entities.positions.get(id).unwrap();
+        let d = (p.0 - pos.0, p.1 - pos.1);
+        let dist = d.0.hypot(d.1);
+        let vel = world.entities.velocities.entry(*id).or_insert((0.0, 0.0));
+        if dist > MAGNET_RADIUS {
+            *vel = (0.0, 0.0);
+            continue;
+        }
+        if dist > PICKUP_RADIUS {
+            *vel = (d.0 / dist * MAGNET_SPEED, d.1 / dist * MAGNET_SPEED);
+            continue;
+        }
+
+        let max_stack = match world.items.get(&dropped.stack.item) {
+            Some(def) => def.stack,
+            None => default_stack(),
+        };
+        let left = world.player.inventory.add(&dropped.stack, max_stack);
+        if left == dropped.stack.count {
+            *vel = (0.0, 0.0);
+            continue; // inventory full
+        }
+        world.events.push(GameEvent::PickedUp);
+        dropped.stack.count = left;
+        if left == 0 {
+            gone.push(*id);
+        }
+    }
+
+    for id in gone {
+        world.entities.despawn(id);
+    }
+}
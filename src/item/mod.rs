@@ -0,0 +1 @@
+pub mod item;
\ No newline at end of file
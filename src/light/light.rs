@@ -0,0 +1,155 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::world::world::{Tile, DAY_LENGTH};
+
+pub const MAX_LIGHT: u8 = 15; //Light level of full daylight and the brightest light sources
+
+// Light level (0 - 15) of the sky at a time, noon is the brightest and midnight the darkest
+pub fn ambient_light(time: u32) -> u8 {
+    let day = (time % DAY_LENGTH) as f64 / DAY_LENGTH as f64;
+    // noon at 0.5, midnight at 0.0
+    let sun = (1.0 - (day * std::f64::consts::TAU).cos()) / 2.0;
+    (sun * MAX_LIGHT as f64).round() as u8
+}
+
+// Spreads light from a tile to its neighbours, losing one level per tile. Solid tiles are lit
+// but stop the light from going further (except the source itself), so do unloaded tiles
+fn propagate(
+    levels: &mut HashMap<(i32, i32), u8>,
+    world: &HashMap<(i32, i32), i32>,
+    tiles: &HashMap<i32, Tile>,
+    source: (i32, i32),
+    level: u8,
+) {
+    let mut queue = VecDeque::new();
+    queue.push_back((source, level));
+    while let Some((pos, level)) = queue.pop_front() {
+        if levels.get(&pos).is_some_and(|l| *l >= level) {
+            continue;
+        }
+        levels.insert(pos, level);
+        if level <= 1 {
+            continue;
+        }
+        let solid = world
+            .get(&pos)
+            .and_then(|id| tiles.get(id))
+            .is_none_or(|t| t.solid);
+        if solid && pos != source {
+            continue;
+        }
+        for d in [(0, -1), (1, 0), (0, 1), (-1, 0)] {
+            let next = (pos.0 + d.0, pos.1 + d.1);
+            if world.contains_key(&next) {
+                queue.push_back((next, level - 1));
+            }
+        }
+    }
+}
+
+// Light of one source on its own
+fn flood(
+    world: &HashMap<(i32, i32), i32>,
+    tiles: &HashMap<i32, Tile>,
+    source: (i32, i32),
+    level: u8,
+) -> HashMap<(i32, i32), u8> {
+    let mut levels = HashMap::new();
+    propagate(&mut levels, world, tiles, source, level);
+    levels
+}
+
+//Light from light emitting tiles and the player's lantern, rebuilt only around what changed
+pub struct LightMap {
+    levels: HashMap<(i32, i32), u8>, //Light from tiles, the brightest source at every tile
+    sources: HashMap<(i32, i32), HashMap<(i32, i32), u8>>, //Light of every light emitting tile
+    built: bool,                     //If the whole world was searched for sources
+    lantern: HashMap<(i32, i32), u8>, //Light from the player's lantern
+    lantern_at: Option<((i32, i32), u8, u32)>, //Tile, level and revision of the lantern light
+}
+
+impl LightMap {
+    pub fn new() -> Self {
+        Self {
+            levels: HashMap::new(),
+            sources: HashMap::new(),
+            built: false,
+            lantern: HashMap::new(),
+            lantern_at: None,
+        }
+    }
+
+    // Relights the sources near changed tiles (the first time every source in the world), and
+    // the lantern if the player moved
+    pub fn update(
+        &mut self,
+        world: &HashMap<(i32, i32), i32>,
+        tiles: &HashMap<i32, Tile>,
+        changed: &[(i32, i32)],
+        revision: u32,
+        player: (i32, i32),
+        lantern: u8,
+    ) {
+        let light_at = |pos: &(i32, i32)| {
+            world
+                .get(pos)
+                .and_then(|id| tiles.get(id))
+                .map_or(0, |t| t.light)
+        };
+        let mut relit = false;
+        if !self.built {
+            self.built = true;
+            for pos in world.keys() {
+                let light = light_at(pos);
+                if light > 0 {
+                    self.sources.insert(*pos, flood(world, tiles, *pos, light));
+                }
+            }
+            relit = true;
+        } else if !changed.is_empty() {
+            // a changed tile can start or stop giving off light, or block or open the way for
+            // the light of sources in reach of it (unloaded tiles block light too)
+            let mut redo: HashSet<(i32, i32)> = changed.iter().copied().collect();
+            for pos in self.sources.keys() {
+                let near = changed
+                    .iter()
+                    .any(|c| (c.0 - pos.0).abs() + (c.1 - pos.1).abs() <= MAX_LIGHT as i32);
+                if near {
+                    redo.insert(*pos);
+                }
+            }
+            for pos in redo {
+                relit |= self.sources.remove(&pos).is_some();
+                let light = light_at(&pos);
+                if light > 0 {
+                    self.sources.insert(pos, flood(world, tiles, pos, light));
+                    relit = true;
+                }
+            }
+        }
+        if relit {
+            self.levels.clear();
+            for source in self.sources.values() {
+                for (pos, level) in source.iter() {
+                    let l = self.levels.entry(*pos).or_insert(0);
+                    *l = (*l).max(*level);
+                }
+            }
+        }
+
+        if self.lantern_at != Some((player, lantern, revision)) {
+            self.lantern_at = Some((player, lantern, revision));
+            self.lantern.clear();
+            if lantern > 0 {
+                propagate(&mut self.lantern, world, tiles, player, lantern);
+            }
+        }
+    }
+
+    // Light level at a tile from light sources only (no daylight)
+    pub fn get(&self, tile: (i32, i32)) -> u8 {
+        let from_tiles = *self.levels.get(&tile).unwrap_or(&0);
+        let from_lantern = *self.lantern.get(&tile).unwrap_or(&0);
+        from_tiles.max(from_lantern)
+    }
+}
@@ -1,64 +1,228 @@
 extern crate sdl2;
-use std::{collections::HashSet, path::Path, time::Duration};
+use std::{
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::{Duration, Instant},
+};
 
-use sdl2::{event::Event, keyboard::{Keycode, Mod}, TimerSubsystem};
+use sdl2::{
+    event::{Event, WindowEvent},
+    keyboard::Keycode,
+};
 
 mod world;
-use world::world::World;
+mod audio;
+use audio::audio::{Audio, AudioBackend, MixerAudio, NullAudio, SOUNDS_PATH};
 mod renderer;
-use renderer::renderer::Renderer;
+use renderer::{
+    atlas::Atlas,
+    backend::{CanvasBackend, RenderBackend},
+    renderer::Renderer,
+    snapshot::run_snapshots,
+};
 mod player;
-use player::player::Player;
 mod gui;
+mod input;
+use input::input::{Bindings, InputState, BINDINGS_PATH};
+use gui::console::Console;
+mod save;
+use save::save::{load_or_generate, save_world};
+mod settings;
+use settings::settings::{Settings, SETTINGS_PATH};
+mod combat;
+mod entity;
+mod event;
+mod item;
+mod light;
+mod mob;
+mod pathfinding;
+mod state;
+use state::{
+    menus::MainMenu,
+    state::{Game, StateStack},
+};
+mod terminal;
+use terminal::terminal::run_terminal;
+mod vision;
+
+const TICK: Duration = Duration::from_nanos(1_000_000_000 / 30); //World ticks 30 times a second
+const MAX_TICKS_PER_FRAME: u32 = 5;
 
 pub fn main() {
-    let screen_area = (800, 600);
+    // settings come from the settings file, command line options override them. The file's
+    // own values are kept apart so the menus don't save the options into it
+    let args: Vec<String> = std::env::args().collect();
+    let saved_settings = Settings::load(Path::new(SETTINGS_PATH));
+    let mut settings = saved_settings.clone();
+    if let Err(e) = settings.apply_args(&args) {
+        println!("{}", e);
+        std::process::exit(2);
+    }
+    let font_path = Path::new(&settings.font);
+
+    // --snapshot / --bless check the renderer against golden images without opening a window
+    if args.iter().any(|a| a == "--snapshot" || a == "--bless") {
+        let ttf_context = sdl2::ttf::init().map_err(|e| e.to_string()).unwrap();
+        let bless = args.iter().any(|a| a == "--bless");
+        if !run_snapshots(&ttf_context, bless) {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // --script <file> runs console commands on every world that gets opened
+    let script = args
+        .iter()
+        .position(|a| a == "--script")
+        .and_then(|i| args.get(i + 1))
+        .map(PathBuf::from);
+
+    // --terminal plays in the terminal with ANSI colors instead of opening a window
+    if args.iter().any(|a| a == "--terminal") {
+        let save_dir = Path::new(&settings.save_dir).join("world");
+        let mut world = load_or_generate(&save_dir, settings.seed, settings.gen_radius).unwrap();
+        let tile_size = settings.tile_size;
+        if let Some(script) = &script {
+            let mut console = Console::new();
+            if let Err(e) = console.run_script(&mut world, script, tile_size) {
+                println!("Could not run {}: {}", script.display(), e);
+            }
+        }
+        run_terminal(&mut world, tile_size);
+        if let Err(e) = save_world(&world, &save_dir, tile_size) {
+            println!("Could not save world: {}", e);
+        }
+        return;
+    }
 
     let sdl_context = sdl2::init().unwrap();
     let video_subsystem = sdl_context.video().unwrap();
     let ttf_context = sdl2::ttf::init().map_err(|e| e.to_string()).unwrap();
 
-    let window = video_subsystem
-        .window("Rust Game", screen_area.0, screen_area.1)
-        .position_centered()
-        .build()
-        .unwrap();
+    let mut window = video_subsystem.window("Rust Game", settings.width, settings.height);
+    window.position_centered();
+    if settings.fullscreen {
+        window.fullscreen_desktop();
+    }
+    if settings.resizable {
+        window.resizable();
+    }
+    if settings.high_dpi {
+        window.allow_highdpi();
+    }
+    let window = window.build().unwrap();
 
-    let mut canvas = window.into_canvas().build().unwrap();
+    let mut canvas = window.into_canvas();
+    if settings.vsync {
+        canvas = canvas.present_vsync();
+    }
+    let canvas = canvas.build().unwrap();
+    let texture_creator = canvas.texture_creator();
+    let mut canvas = CanvasBackend::new(canvas, &texture_creator);
+    canvas.fit_window(settings.logical_size());
+    let mut fullscreen = settings.fullscreen; //What the window is in, the settings can change it
 
-    let mut world = World::new(Player::new());
-    world.gen((-100, 100), (-100, 100)); //initial world generation around the player
-    world.print_debug();
-    let mut render = Renderer::new(screen_area.0, screen_area.1);
-    let mut texture_creator = canvas.texture_creator();
-    let font_path: &Path = Path::new(&"assets/fonts/vcr_osd_mono.ttf");
-    let mut font = ttf_context.load_font(font_path, 32).unwrap();
+    let (width, height) = canvas.size();
+    let mut render = Renderer::new(width, height);
+    render.tile_size = settings.tile_size;
+    render.atlas = match Atlas::load(Path::new(&"assets/textures/tiles.toml")) {
+        Ok(atlas) => Some(atlas),
+        Err(e) => {
+            println!("Could not load tile atlas: {}", e);
+            None
+        }
+    };
+    let font = ttf_context
+        .load_font(font_path, settings.scaled_font_size())
+        .unwrap();
 
-    let mut keys_pressed: HashSet<Keycode> = HashSet::new();
-    let mut running = true;
+    // controllers are optional, the game runs without the subsystem
+    let pads = match sdl_context.game_controller() {
+        Ok(pads) => Some(pads),
+        Err(e) => {
+            println!("Could not start controller support: {}", e);
+            None
+        }
+    };
+    let input = InputState::new(Bindings::load(Path::new(BINDINGS_PATH)), pads);
+    // without a sound card the game plays silently
+    let backend: Box<dyn AudioBackend> = match MixerAudio::open(&sdl_context) {
+        Ok(mixer) => Box::new(mixer),
+        Err(e) => {
+            println!("Could not start audio: {}", e);
+            Box::new(NullAudio::new(Rc::default()))
+        }
+    };
+    let mut audio = Audio::new(backend, &settings);
+    audio.load(Path::new(SOUNDS_PATH));
+    let mut game = Game::new(render, input, audio, settings, saved_settings);
+    game.script = script;
+    let mut stack = StateStack::new(Box::new(MainMenu::new()));
     let mut event_queue = sdl_context.event_pump().unwrap();
 
-    while running {
+    let mut last = Instant::now();
+    let mut lag = Duration::ZERO;
+    while stack.running {
+        let frame_start = Instant::now();
+        lag += frame_start - last;
+        last = frame_start;
+
         for event in event_queue.poll_iter() {
             match event {
-                Event::Quit { .. } => {
-                    running = false;
-                }
-                Event::KeyDown { keycode, .. } => {
-                    keys_pressed.insert(keycode.unwrap());
-                }
-                Event::KeyUp { keycode, .. } => {
-                    keys_pressed.remove(&keycode.unwrap());
+                Event::Quit { .. } => stack.running = false,
+                Event::Window {
+                    win_event: WindowEvent::SizeChanged(..),
+                    ..
+                } => {
+                    canvas.fit_window(game.settings.logical_size());
+                    let (width, height) = canvas.size();
+                    game.render.resize(width, height);
                 }
+                // F11 toggles fullscreen in any state
+                Event::KeyDown {
+                    keycode: Some(Keycode::F11),
+                    repeat: false,
+                    ..
+                } => game.settings.fullscreen = !game.settings.fullscreen,
                 _ => {}
             }
+            // held keys are tracked for every state, so releasing one in a menu isn't missed
+            game.input.handle_event(&event);
+            stack.handle_event(&mut game, &event);
+        }
+        if game.settings.fullscreen != fullscreen {
+            fullscreen = game.settings.fullscreen;
+            canvas.set_fullscreen(fullscreen);
+        }
+        //Game loop, the world ticks at a fixed rate whatever the frame rate is. After a long
+        //stall the missed ticks are dropped instead of all running at once
+        let mut ticks = 0;
+        while lag >= TICK && ticks < MAX_TICKS_PER_FRAME {
+            stack.update(&mut game);
+            let tile_size = game.render.tile_size;
+            game.audio
+                .update(game.world.as_ref(), &game.settings, tile_size);
+            if let Some(world) = &mut game.world {
+                world.events.clear();
+            }
+            lag -= TICK;
+            ticks += 1;
+        }
+        if ticks == MAX_TICKS_PER_FRAME {
+            lag = Duration::ZERO;
+        }
+        stack.render(&mut game, &mut canvas, &font);
+        canvas.present();
+        game.render.gui.events.clear();
+
+        if game.settings.fps > 0 {
+            let frame = Duration::from_secs_f64(1.0 / game.settings.fps as f64);
+            let spent = frame_start.elapsed();
+            if spent < frame {
+                ::std::thread::sleep(frame - spent);
+            }
         }
-        //Game loop
-        world
-            .player
-            .input(&keys_pressed, &world.world, &world.tiles, render.tile_size);
-        let m_coords = (event_queue.mouse_state().x(), event_queue.mouse_state().y());
-        render.render(&mut canvas, &mut world, &font, &texture_creator, m_coords);
-        ::std::thread::sleep(Duration::new(0, 1_000_000_000u32 / 30));
     }
+
+    game.save();
 }
@@ -0,0 +1,231 @@
+use rand::Rng;
+use sdl2::pixels::Color;
+use serde::Deserialize;
+use std::{fs, path::Path};
+
+use crate::combat::combat::{DamageType, Health};
+use crate::entity::entity::{Ai, Sprite};
+use crate::world::world::{chunk_of, collides_with, Tile, World};
+
+//Where and how often a mob appears
+#[derive(Deserialize)]
+pub struct SpawnRule {
+    pub tiles: Vec<i32>,       //Tile ids the mob can spawn on
+    pub light: (u8, u8),       //Minimum and maximum light level
+    pub max_per_chunk: usize,  //Density cap
+    pub distance: (i32, i32),  //Minimum and maximum distance from the player (in tiles)
+    pub despawn: i32,          //Distance from the player at which the mob is removed (in tiles)
+    pub chance: f64,           //Chance of a spawn attempt every tick
+}
+
+//Items left behind when a mob dies
+#[derive(Deserialize)]
+pub struct Drop {
+    pub item: String,
+    pub count: (u32, u32), //Minimum and maximum amount
+}
+
+fn default_damage_type() -> DamageType {
+    DamageType::Bite
+}
+
+//One kind of creature, loaded from assets/data/mobs.toml
+#[derive(Deserialize)]
+pub struct MobDef {
+    pub name: String,
+    pub color: (u8, u8, u8),
+    pub symbol: char,
+    pub size: (u32, u32),
+    pub speed: f64,
+    pub health: i32,
+    #[serde(default)]
+    pub armor: i32,
+    #[serde(default)]
+    pub hostile: bool,
+    #[serde(default)]
+    pub aquatic: bool,
+    #[serde(default)]
+    pub sight: f64,
+    #[serde(default)]
+    pub reach: f64,
+    #[serde(default)]
+    pub damage: i32,
+    #[serde(default = "default_damage_type")]
+    pub damage_type: DamageType,
+    #[serde(default)]
+    pub knockback: f64,
+    #[serde(default)]
+    pub cooldown: i32,
+    #[serde(default)]
+    pub drops: Vec<Drop>,
+    pub spawn: SpawnRule,
+}
+
+#[derive(Deserialize)]
+struct MobFile {
+    mob: Vec<MobDef>,
+}
+
+// Fixes values spawning and drops can't work with, saying which mob had them. Returns false
+// for a mob that would despawn where it spawns, it is left out
+fn validate(def: &mut MobDef) -> bool {
+    let rule = &mut def.spawn;
+    if !(0.0..=1.0).contains(&rule.chance) {
+        println!("{}: spawn chance {} is not 0 - 1", def.name, rule.chance);
+        rule.chance = if rule.chance.is_nan() {
+            0.0
+        } else {
+            rule.chance.clamp(0.0, 1.0)
+        };
+    }
+    if rule.distance.0 > rule.distance.1 {
+        println!("{}: spawn distance minimum is above the maximum", def.name);
+        rule.distance = (rule.distance.1, rule.distance.0);
+    }
+    for drop in def.drops.iter_mut() {
+        if drop.count.0 > drop.count.1 {
+            println!("{}: {} drop minimum is above the maximum", def.name, drop.item);
+            drop.count = (drop.count.1, drop.count.0);
+        }
+    }
+    if def.spawn.despawn <= def.spawn.distance.1 {
+        println!(
+            "{}: despawn distance {} is not above the spawn distance {}, it won't spawn",
+            def.name, def.spawn.despawn, def.spawn.distance.1
+        );
+        return false;
+    }
+    true
+}
+
+// Loads mob definitions, a missing or broken file just means nothing spawns
+pub fn load_mobs(path: &Path) -> Vec<MobDef> {
+    let text = match fs::read_to_string(path) {
+        Ok(t) => t,
+        Err(e) => {
+            println!("Could not read {}: {}", path.display(), e);
+            return Vec::new();
+        }
+    };
+    match toml::from_str::<MobFile>(&text) {
+        Ok(mut f) => {
+            f.mob.retain_mut(validate);
+            f.mob
+        }
+        Err(e) => {
+            println!("Could not parse {}: {}", path.display(), e);
+            Vec::new()
+        }
+    }
+}
+
+// Tries to spawn every kind of mob around the player, following its spawn rule
+pub fn spawn_system(world: &mut World, tile_size: i32) {
+    let p = world.player.pos;
+    let pt: (i32, i32) = (
+        (p.0 / tile_size as f64).floor() as i32,
+        (p.1 / tile_size as f64).floor() as i32,
+    );
+
+    for kind in 0..world.mobs.len() {
+        let def = &world.mobs[kind];
+        let rule = &def.spawn;
+        if !world.rng.gen_bool(rule.chance) {
+            continue;
+        }
+
+        // random tile in a ring around the player
+        let angle: f64 = world.rng.gen_range(0.0..std::f64::consts::TAU);
+        let dist: f64 = world.rng.gen_range(rule.distance.0 as f64..=rule.distance.1 as f64);
+        let tile = (
+            pt.0 + (angle.cos() * dist).round() as i32,
+            pt.1 + (angle.sin() * dist).round() as i32,
+        );
+
+        // only spawn on generated tiles, never generate terrain for a mob
+        let tid = match world.world.get(&tile) {
+            Some(id) => *id,
+            None => continue,
+        };
+        if !rule.tiles.contains(&tid) {
+            continue;
+        }
+        let light = world.light_level(tile);
+        if light < rule.light.0 || light > rule.light.1 {
+            continue;
+        }
+
+        let chunk = chunk_of(tile);
+        let in_chunk = world
+            .entities
+            .mobs
+            .iter()
+            .filter(|(id, k)| {
+                **k == kind && {
+                    let pos = world.entities.positions.get(id).unwrap();
+                    let t = (
+                        (pos.0 / tile_size as f64).floor() as i32,
+                        (pos.1 / tile_size as f64).floor() as i32,
+                    );
+                    chunk_of(t) == chunk
+                }
+            })
+            .count();
+        if in_chunk >= rule.max_per_chunk {
+            continue;
+        }
+
+        let pos = (
+            (tile.0 as f64 + 0.5) * tile_size as f64,
+            (tile.1 as f64 + 0.5) * tile_size as f64,
+        );
+        let aquatic = def.aquatic;
+        let blocked = |t: &Tile| if aquatic { !t.liquid } else { t.solid };
+        if collides_with(pos, def.size, &world.world, &world.tiles, tile_size, blocked) {
+            continue;
+        }
+
+        let mut ai = Ai::new(def.speed);
+        ai.hostile = def.hostile;
+        ai.sight = def.sight;
+        ai.reach = def.reach;
+        ai.damage = def.damage;
+        ai.damage_type = def.damage_type;
+        ai.knockback = def.knockback;
+        ai.cooldown = def.cooldown;
+
+        let mut health = Health::new(def.health);
+        health.armor = def.armor;
+
+        let sprite = Sprite {
+            color: Color::RGB(def.color.0, def.color.1, def.color.2),
+            symbol: def.symbol,
+        };
+        let id = world.entities.spawn(pos, def.size, sprite);
+        world.entities.ais.insert(id, ai);
+        world.entities.healths.insert(id, health);
+        world.entities.mobs.insert(id, kind);
+        if def.aquatic {
+            world.entities.aquatic.insert(id);
+        }
+    }
+}
+
+// Removes mobs that wandered too far from the player
+pub fn despawn_system(world: &mut World, tile_size: i32) {
+    let p = world.player.pos;
+    let far: Vec<_> = world
+        .entities
+        .mobs
+        .iter()
+        .filter(|(id, kind)| {
+            let pos = world.entities.positions.get(id).unwrap();
+            let max = (world.mobs[**kind].spawn.despawn * tile_size) as f64;
+            (pos.0 - p.0).abs() > max || (pos.1 - p.1).abs() > max
+        })
+        .map(|(id, _)| *id)
+        .collect();
+    for id in far {
+        world.entities.despawn(id);
+    }
+}
@@ -0,0 +1 @@
+pub mod mob;
\ No newline at end of file
@@ -0,0 +1 @@
+pub mod pathfinding;
\ No newline at end of file
@@ -0,0 +1,388 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+};
+
+use crate::world::world::{Tile, World};
+
+pub const SEARCH_BUDGET: usize = 4000; //Max tiles expanded by one search
+const MAX_CACHED: usize = 256; //Cached paths kept before the cache is cleared
+
+const STRAIGHT: i32 = 10; //Cost of moving to a side neighbour
+const DIAGONAL: i32 = 14; //Cost of moving to a corner neighbour (~10 * sqrt(2))
+
+// Gets the size of a hitbox in tiles (rounded up)
+pub fn footprint(size: (u32, u32), tile_size: i32) -> (i32, i32) {
+    (
+        (size.0 as i32 + tile_size - 1) / tile_size,
+        (size.1 as i32 + tile_size - 1) / tile_size,
+    )
+}
+
+// What a search is for: the hitbox footprint in tiles, if the entity swims (only liquid tiles
+// are open to it) and how many tiles the search may expand
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Query {
+    pub hitbox: (i32, i32),
+    pub aquatic: bool,
+    pub budget: usize,
+}
+
+impl Query {
+    pub fn new(hitbox: (i32, i32)) -> Self {
+        Self {
+            hitbox: hitbox,
+            aquatic: false,
+            budget: SEARCH_BUDGET,
+        }
+    }
+
+    fn blocked(&self, t: &Tile) -> bool {
+        if self.aquatic {
+            !t.liquid
+        } else {
+            t.solid
+        }
+    }
+}
+
+// Octile distance, admissible for 8-directional movement
+fn heuristic(a: (i32, i32), b: (i32, i32)) -> i32 {
+    let dx = (a.0 - b.0).abs();
+    let dy = (a.1 - b.1).abs();
+    STRAIGHT * (dx + dy) + (DIAGONAL - 2 * STRAIGHT) * dx.min(dy)
+}
+
+// Checks if a hitbox of the given footprint can stand centered on a tile
+// Tiles that are not generated yet count as blocked
+fn walkable(
+    world: &HashMap<(i32, i32), i32>,
+    tiles: &HashMap<i32, Tile>,
+    tile: (i32, i32),
+    query: &Query,
+) -> bool {
+    let hitbox = query.hitbox;
+    for dy in -(hitbox.1 - 1) / 2..=hitbox.1 / 2 {
+        for dx in -(hitbox.0 - 1) / 2..=hitbox.0 / 2 {
+            let t = match world.get(&(tile.0 + dx, tile.1 + dy)) {
+                Some(id) => tiles.get(id).unwrap(),
+                None => return false,
+            };
+            if query.blocked(t) {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+// A* over the tile grid, returns the tiles to walk through (excluding from, including to)
+// Gives up (returns None) once more than the query's budget of tiles have been expanded
+pub fn search(
+    world: &HashMap<(i32, i32), i32>,
+    tiles: &HashMap<i32, Tile>,
+    from: (i32, i32),
+    to: (i32, i32),
+    query: &Query,
+) -> Option<Vec<(i32, i32)>> {
+    if from == to {
+        return Some(Vec::new());
+    }
+    if !walkable(world, tiles, to, query) {
+        return None;
+    }
+
+    let mut open = BinaryHeap::new();
+    let mut came_from: HashMap<(i32, i32), (i32, i32)> = HashMap::new();
+    let mut cost: HashMap<(i32, i32), i32> = HashMap::new();
+    let mut expanded = 0;
+
+    cost.insert(from, 0);
+    open.push(Reverse((heuristic(from, to), from)));
+
+    while let Some(Reverse((_, current))) = open.pop() {
+        if current == to {
+            // walk back to the start
+            let mut path = vec![current];
+            let mut t = current;
+            while let Some(prev) = came_from.get(&t) {
+                if *prev == from {
+                    break;
+                }
+                path.push(*prev);
+                t = *prev;
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        expanded += 1;
+        if expanded > query.budget {
+            return None;
+        }
+
+        let c = *cost.get(&current).unwrap();
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                if dx == 0 && dy == 0 {
+                    continue;
+                }
+                let n = (current.0 + dx, current.1 + dy);
+                if !walkable(world, tiles, n, query) {
+                    continue;
+                }
+                let step = if dx != 0 && dy != 0 {
+                    // don't cut corners of blocked tiles
+                    if !walkable(world, tiles, (current.0 + dx, current.1), query)
+                        || !walkable(world, tiles, (current.0, current.1 + dy), query)
+                    {
+                        continue;
+                    }
+                    DIAGONAL
+                } else {
+                    STRAIGHT
+                };
+
+                let nc = c + step;
+                if nc < *cost.get(&n).unwrap_or(&i32::MAX) {
+                    cost.insert(n, nc);
+                    came_from.insert(n, current);
+                    open.push(Reverse((nc + heuristic(n, to), n)));
+                }
+            }
+        }
+    }
+    None
+}
+
+// Finds a path for a land entity with a hitbox footprint of hitbox tiles, see search
+pub fn find_path(
+    world: &World,
+    from: (i32, i32),
+    to: (i32, i32),
+    hitbox: (i32, i32),
+) -> Option<Vec<(i32, i32)>> {
+    search(&world.world, &world.tiles, from, to, &Query::new(hitbox))
+}
+
+// Start, goal and what the search was for
+type PathKey = ((i32, i32), (i32, i32), Query);
+
+//Remembers results of searches (including failed ones) until a tile is edited. Generating new
+//tiles keeps them, paths only go over tiles that were already there
+pub struct PathCache {
+    paths: HashMap<PathKey, Option<Vec<(i32, i32)>>>,
+    edits: u32, //World edits the cached paths were found after
+}
+
+impl PathCache {
+    pub fn new() -> Self {
+        Self {
+            paths: HashMap::new(),
+            edits: 0,
+        }
+    }
+
+    // Same as search, but reuses paths found since the last edit to the world
+    pub fn find(
+        &mut self,
+        world: &HashMap<(i32, i32), i32>,
+        tiles: &HashMap<i32, Tile>,
+        edits: u32,
+        from: (i32, i32),
+        to: (i32, i32),
+        query: &Query,
+    ) -> Option<Vec<(i32, i32)>> {
+        if edits != self.edits || self.paths.len() > MAX_CACHED {
+            self.paths.clear();
+            self.edits = edits;
+        }
+        let key = (from, to, *query);
+        if let Some(path) = self.paths.get(&key) {
+            return path.clone();
+        }
+        let path = search(world, tiles, from, to, query);
+        self.paths.insert(key, path.clone());
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sdl2::pixels::Color;
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::player::player::Player;
+
+    fn tile(id: i32, solid: bool) -> Tile {
+        Tile {
+            name: String::new(),
+            id: id,
+            color: Color::BLACK,
+            symbol: ' ',
+            sprite: None,
+            solid: solid,
+            liquid: false,
+            breakable: false,
+            drop: None,
+            edges: Vec::new(),
+            light: 0,
+            material: None,
+        }
+    }
+
+    fn tiles() -> HashMap<i32, Tile> {
+        HashMap::from([(0, tile(0, false)), (1, tile(1, true))])
+    }
+
+    // Builds a map from lines of text: . is floor, # is a wall and anything else is unloaded.
+    // Lines are trimmed and blank ones skipped, so the map can be indented
+    fn map(text: &str) -> HashMap<(i32, i32), i32> {
+        let mut world = HashMap::new();
+        let rows = text.lines().map(str::trim).filter(|r| !r.is_empty());
+        for (y, row) in rows.enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                match c {
+                    '.' => world.insert((x as i32, y as i32), 0),
+                    '#' => world.insert((x as i32, y as i32), 1),
+                    _ => None,
+                };
+            }
+        }
+        world
+    }
+
+    fn find(
+        world: &HashMap<(i32, i32), i32>,
+        from: (i32, i32),
+        to: (i32, i32),
+        budget: usize,
+    ) -> Option<Vec<(i32, i32)>> {
+        let mut query = Query::new((1, 1));
+        query.budget = budget;
+        search(world, &tiles(), from, to, &query)
+    }
+
+    #[test]
+    fn detours_around_a_wall() {
+        let world = map("
+            .....
+            ..#..
+            ..#..
+            ..#..
+            .....
+        ");
+        let path = find(&world, (0, 2), (4, 2), SEARCH_BUDGET).unwrap();
+        assert_eq!(path.last(), Some(&(4, 2)));
+        let mut prev = (0, 2);
+        for t in path.iter() {
+            assert_eq!(world[t], 0, "path goes through a wall at {:?}", t);
+            assert!((t.0 - prev.0).abs() <= 1 && (t.1 - prev.1).abs() <= 1);
+            prev = *t;
+        }
+    }
+
+    #[test]
+    fn solid_or_unreachable_goal_has_no_path() {
+        let world = map("
+            .....
+            .###.
+            .#.#.
+            .###.
+            .....
+        ");
+        assert_eq!(find(&world, (0, 0), (1, 1), SEARCH_BUDGET), None);
+        assert_eq!(find(&world, (0, 0), (2, 2), SEARCH_BUDGET), None);
+    }
+
+    #[test]
+    fn unloaded_tiles_are_blocked() {
+        let world = map("
+            ..?..
+            ..?..
+            .....
+        ");
+        let path = find(&world, (0, 0), (4, 0), SEARCH_BUDGET).unwrap();
+        assert!(path.iter().all(|t| world.contains_key(t)));
+        assert!(path.contains(&(2, 2)));
+
+        let world = map("
+            ..?..
+            ..?..
+            ..?..
+        ");
+        assert_eq!(find(&world, (0, 0), (4, 0), SEARCH_BUDGET), None);
+    }
+
+    #[test]
+    fn does_not_cut_corners() {
+        let world = map("
+            .#
+            #.
+        ");
+        assert_eq!(find(&world, (0, 0), (1, 1), SEARCH_BUDGET), None);
+
+        let world = map("
+            ..
+            #.
+        ");
+        assert_eq!(
+            find(&world, (0, 0), (1, 1), SEARCH_BUDGET),
+            Some(vec![(1, 0), (1, 1)])
+        );
+    }
+
+    #[test]
+    fn gives_up_when_the_budget_runs_out() {
+        let world = map(&"....................\n".repeat(20));
+        assert_eq!(find(&world, (0, 0), (19, 19), 5), None);
+        assert!(find(&world, (0, 0), (19, 19), SEARCH_BUDGET).is_some());
+    }
+
+    #[test]
+    fn aquatic_entities_only_swim() {
+        let mut tiles = tiles();
+        tiles.get_mut(&1).unwrap().liquid = true;
+        let world = map("
+            .....
+            #####
+        ");
+        let mut query = Query::new((1, 1));
+        query.aquatic = true;
+        assert_eq!(
+            search(&world, &tiles, (0, 1), (4, 1), &query).map(|p| p.len()),
+            Some(4)
+        );
+        assert_eq!(search(&world, &tiles, (0, 0), (4, 0), &query), None);
+    }
+
+    #[test]
+    fn cache_is_cleared_when_a_tile_is_edited() {
+        let mut world = map(".....");
+        let tiles = tiles();
+        let query = Query::new((1, 1));
+        let mut cache = PathCache::new();
+        let path = cache.find(&world, &tiles, 0, (0, 0), (4, 0), &query);
+        assert!(path.is_some());
+
+        world.insert((2, 0), 1);
+        // no edit counted yet, the old path is still cached
+        let cached = cache.find(&world, &tiles, 0, (0, 0), (4, 0), &query);
+        assert_eq!(cached, path);
+        assert_eq!(cache.find(&world, &tiles, 1, (0, 0), (4, 0), &query), None);
+    }
+
+    #[test]
+    fn generating_tiles_is_not_an_edit() {
+        let mut world = World::new(Player::new());
+        world.generate_tile(0, 0);
+        world.generate_tile(1, 0);
+        world.generate_tile(1, 0);
+        assert_eq!(world.edits, 0);
+
+        world.set_tile((1, 0), 6);
+        assert_eq!(world.edits, 1);
+        assert_eq!(find_path(&world, (0, 0), (1, 0), (1, 1)), None);
+    }
+}
@@ -1,15 +1,28 @@
-use sdl2::{keyboard::{Keycode, Scancode, Mod}, pixels::Color, render::Canvas, video::Window};
+use sdl2::{pixels::Color, render::Canvas, video::Window};
 use std::collections::{HashMap, HashSet};
 
-use crate::world::world::Tile;
+use crate::combat::combat::Health;
+use crate::input::input::Action;
+use crate::item::item::{Inventory, HOTBAR_SIZE};
+use crate::vision::vision::Explored;
+use crate::world::world::{collides, Tile};
 
 pub struct Player {
     pub pos: (f64, f64),
     pub color: Color,
     pub size: (u32, u32),
-    pub health: i32,
+    pub health: Health,
     pub stamina: i32,
     pub hunger: i32,
+    pub facing: (f64, f64),   //Direction the player last moved in (unit vector)
+    pub attacking: bool,      //If the attack key is held down
+    pub attack_cooldown: i32, //Ticks until the player can attack again
+    pub inventory: Inventory,
+    pub lantern: u8, //Light level of the lantern the player carries (0 - 15)
+    pub explored: Explored, //Tiles the player has seen
+    pub speed: f64,         //Multiplier on walking and sprinting speed
+    pub noclip: bool,       //Walk through solid tiles
+    pub stride: f64,        //Distance walked since the last footstep
     mv_mult: f64,
     walk_mult: f64,
     sprint_mult: f64,
@@ -21,9 +34,18 @@ impl Player {
             pos: (25.0, 25.0),
             color: Color::BLACK,
             size: (20, 20),
-            health: 100,
+            health: Health::new(100),
             stamina: 100,
             hunger: 100,
+            facing: (0.0, 1.0),
+            attacking: false,
+            attack_cooldown: 0,
+            inventory: Inventory::new(),
+            lantern: 8,
+            explored: Explored::new(),
+            speed: 1.0,
+            noclip: false,
+            stride: 0.0,
             mv_mult: 3.0,
             walk_mult: 3.0,
             sprint_mult: 5.0,
@@ -36,56 +58,7 @@ impl Player {
         tiles: &HashMap<i32, Tile>,
         tile_size: i32,
     ) -> bool {
-        // player position translated to tiles and rounded (what tile the player is currently on)
-        let pt: (i32, i32) = (
-            (self.pos.0 as f64 / tile_size as f64).floor() as i32,
-            (self.pos.1 as f64 / tile_size as f64).floor() as i32,
-        );
-
-        let dir = vec![(0, 1), (0, -1), (1, 0), (-1, 0), (0, 0)];
-
-        // let ptid: i32;
-        // if world.contains_key(&pt) {
-        //     ptid = world.get(&pt).unwrap().clone();
-        // } else {
-        //     ptid = -1;
-        // }
-        // let ptile: &Tile = tiles.get(&ptid).unwrap();
-        // println!("{}", ptile.name);
-
-        for d in dir {
-            let n: (i32, i32) = (pt.0 + d.0, pt.1 + d.1);
-            let tid: i32;
-            if world.contains_key(&n) {
-                tid = world.get(&n).unwrap().clone();
-            } else {
-                tid = -1;
-            }
-            let t: &Tile = tiles.get(&tid).unwrap();
-
-            if !t.solid {
-                continue;
-            }
-
-            //check for collision
-            // Player: self.pos +/- self.size / 2
-            // Tile: n +/0 tile_size
-            let p_left: f64 = self.pos.0 - (self.size.0 as f64 / 2.0);
-            let p_right: f64 = self.pos.0 + (self.size.0 as f64 / 2.0);
-            let p_up: f64 = self.pos.1 - (self.size.1 as f64 / 2.0);
-            let p_down: f64 = self.pos.1 + (self.size.1 as f64 / 2.0);
-
-            let t_left: f64 = (n.0 * tile_size) as f64;
-            let t_right: f64 = ((n.0 + 1) * tile_size) as f64;
-            let t_up: f64 = (n.1 * tile_size) as f64;
-            let t_down: f64 = ((n.1 + 1) * tile_size) as f64;
-
-            if (p_down <= t_up || p_up >= t_down || p_right <= t_left || p_left >= t_right) {
-                continue;
-            }
-            return true;
-        }
-        return false;
+        collides(self.pos, self.size, world, tiles, tile_size)
     }
 
     // movement function, takes in tuple for delta
@@ -96,38 +69,67 @@ impl Player {
         tiles: &HashMap<i32, Tile>,
         tile_size: i32,
     ) {
-        self.pos.0 += delta.0 * self.mv_mult;
-        self.pos.1 += delta.1 * self.mv_mult;
-        if self.collision(world, tiles, tile_size) {
-            self.pos.0 -= delta.0 * self.mv_mult;
-            self.pos.1 -= delta.1 * self.mv_mult;
+        let step = self.mv_mult * self.speed;
+        self.pos.0 += delta.0 * step;
+        self.pos.1 += delta.1 * step;
+        if !self.noclip && self.collision(world, tiles, tile_size) {
+            self.pos.0 -= delta.0 * step;
+            self.pos.1 -= delta.1 * step;
         }
     }
 
-    // takes in input
+    // takes in the actions held down this tick and the movement stick (0 without a controller)
     pub fn input(
         &mut self,
-        keys_pressed: &HashSet<Keycode>,
+        actions: &HashSet<Action>,
+        stick: (f64, f64),
         world: &HashMap<(i32, i32), i32>,
         tiles: &HashMap<i32, Tile>,
         tile_size: i32,
     ) {
-        if keys_pressed.get(&Keycode::LShift).is_some() {
+        if actions.contains(&Action::Sprint) {
             self.mv_mult = self.sprint_mult;
         } else {
             self.mv_mult = self.walk_mult;
         }
-        if keys_pressed.get(&Keycode::W).is_some() {
-            self.mv((0.0, -1.0), world, tiles, tile_size);
+        self.attacking = actions.contains(&Action::Attack);
+
+        for i in 0..HOTBAR_SIZE {
+            if actions.contains(&Action::Hotbar(i)) {
+                self.inventory.selected = i;
+            }
+        }
+
+        let mut dir: (f64, f64) = (0.0, 0.0);
+        if actions.contains(&Action::MoveUp) {
+            dir.1 -= 1.0;
+        }
+        if actions.contains(&Action::MoveDown) {
+            dir.1 += 1.0;
         }
-        if keys_pressed.get(&Keycode::S).is_some() {
-            self.mv((0.0, 1.0), world, tiles, tile_size);
+        if actions.contains(&Action::MoveLeft) {
+            dir.0 -= 1.0;
         }
-        if keys_pressed.get(&Keycode::A).is_some() {
-            self.mv((-1.0, 0.0), world, tiles, tile_size);
+        if actions.contains(&Action::MoveRight) {
+            dir.0 += 1.0;
+        }
+        // a tilted stick moves slower than a full one, the keys win if both are used
+        if dir == (0.0, 0.0) {
+            dir = stick;
+        }
+
+        // remember where the player is looking for attacks
+        if dir != (0.0, 0.0) {
+            let len = (dir.0 * dir.0 + dir.1 * dir.1).sqrt();
+            self.facing = (dir.0 / len, dir.1 / len);
+        }
+
+        // each axis moves on its own so the player slides along walls
+        if dir.1 != 0.0 {
+            self.mv((0.0, dir.1), world, tiles, tile_size);
         }
-        if keys_pressed.get(&Keycode::D).is_some() {
-            self.mv((1.0, 0.0), world, tiles, tile_size);
+        if dir.0 != 0.0 {
+            self.mv((dir.0, 0.0), world, tiles, tile_size);
         }
     }
 }
@@ -0,0 +1,38 @@
+use sdl2::{image::LoadSurface, rect::Rect, surface::Surface};
+use serde::Deserialize;
+use std::{collections::HashMap, fs, path::Path};
+
+#[derive(Deserialize)]
+struct AtlasFile {
+    image: String, //Path of the image, relative to the frame map
+    frames: HashMap<String, (i32, i32, u32, u32)>, //x, y, width, height
+}
+
+//One image holding many sprites, with the position of every sprite by name
+pub struct Atlas {
+    pub sheet: Surface<'static>,
+    pub frames: HashMap<String, Rect>,
+}
+
+impl Atlas {
+    // Loads a frame map (toml) and the image it points to
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let file: AtlasFile = toml::from_str(&text).map_err(|e| e.to_string())?;
+        let image = path.parent().unwrap_or(Path::new("")).join(&file.image);
+        let sheet = Surface::from_file(&image)?;
+
+        let mut frames = HashMap::new();
+        for (name, f) in file.frames {
+            frames.insert(name, Rect::new(f.0, f.1, f.2, f.3));
+        }
+        Ok(Self {
+            sheet: sheet,
+            frames: frames,
+        })
+    }
+
+    pub fn frame(&self, name: &str) -> Option<Rect> {
+        self.frames.get(name).copied()
+    }
+}
@@ -0,0 +1,75 @@
+use sdl2::rect::Rect;
+use std::collections::HashMap;
+
+use crate::world::world::Tile;
+
+// Sides and corners of a tile as bits of an edge mask, with their offset and frame suffix.
+// Corners only count when neither side next to them is set
+pub const SIDES: [(u8, (i32, i32), &str); 8] = [
+    (1, (0, -1), "n"),
+    (2, (1, 0), "e"),
+    (4, (0, 1), "s"),
+    (8, (-1, 0), "w"),
+    (16, (1, -1), "ne"),
+    (32, (1, 1), "se"),
+    (64, (-1, 1), "sw"),
+    (128, (-1, -1), "nw"),
+];
+
+// Gets the edges neighbouring tiles draw over the tile at pos, as (neighbour id, edge mask)
+// pairs ordered by id so overlapping edges always stack the same way
+pub fn edge_masks(
+    world: &HashMap<(i32, i32), i32>,
+    tiles: &HashMap<i32, Tile>,
+    pos: (i32, i32),
+) -> Vec<(i32, u8)> {
+    let mut masks: Vec<(i32, u8)> = Vec::new();
+    let id = match world.get(&pos) {
+        Some(id) => *id,
+        None => return masks,
+    };
+    for (bit, d, _) in SIDES.iter() {
+        let n = match world.get(&(pos.0 + d.0, pos.1 + d.1)) {
+            Some(n) => *n,
+            None => continue,
+        };
+        if n == id || !tiles.get(&n).is_some_and(|t| t.edges.contains(&id)) {
+            continue;
+        }
+        match masks.iter_mut().find(|(m, _)| *m == n) {
+            Some((_, mask)) => *mask |= bit,
+            None => masks.push((n, *bit)),
+        }
+    }
+
+    // a corner is already covered by either side next to it
+    for (_, mask) in masks.iter_mut() {
+        for (corner, c, _) in SIDES[4..].iter() {
+            let covered = SIDES[..4]
+                .iter()
+                .any(|(side, d, _)| *mask & side != 0 && (d.0 == c.0 || d.1 == c.1));
+            if covered {
+                *mask &= !corner;
+            }
+        }
+    }
+    masks.sort_by_key(|(n, _)| *n);
+    masks
+}
+
+// Gets the part of a tile's rect an edge covers, used when there is no sprite for it
+pub fn edge_rect(rect: Rect, bit: u8) -> Rect {
+    let t = (rect.width() / 4).max(1);
+    let (x, y, w, h) = (rect.x(), rect.y(), rect.width(), rect.height());
+    let (r, b) = (x + (w - t) as i32, y + (h - t) as i32);
+    match bit {
+        1 => Rect::new(x, y, w, t),
+        2 => Rect::new(r, y, t, h),
+        4 => Rect::new(x, b, w, t),
+        8 => Rect::new(x, y, t, h),
+        16 => Rect::new(r, y, t, t),
+        32 => Rect::new(r, b, t, t),
+        64 => Rect::new(x, b, t, t),
+        _ => Rect::new(x, y, t, t),
+    }
+}
@@ -0,0 +1,358 @@
+use sdl2::{
+    image::SaveSurface,
+    pixels::{Color, PixelFormatEnum},
+    rect::Rect,
+    render::{BlendMode, Canvas, RenderTarget, Texture, TextureCreator},
+    surface::{Surface, SurfaceRef},
+    ttf::Font,
+    video::{FullscreenType, Window},
+};
+use std::collections::{hash_map::Entry, HashMap};
+
+use crate::renderer::{
+    renderer::Align,
+    text_cache::{TextCache, TextKey, TEXT_CACHE_SIZE},
+};
+
+// Everything the renderer and gui need to draw a frame, so they can draw to a window,
+// an SDL surface or plain memory (for tests) alike
+pub trait RenderBackend {
+    // Width and height of the drawing area in pixels
+    fn size(&self) -> (u32, u32);
+    // Fills a rect, blending it over what is already there if the color is transparent
+    fn fill_rect(&mut self, rect: Rect, color: Color);
+    // Draws the 1 pixel outline of a rect
+    fn draw_rect(&mut self, rect: Rect, color: Color) {
+        let (x, y, w, h) = (rect.x(), rect.y(), rect.width(), rect.height());
+        self.fill_rect(Rect::new(x, y, w, 1), color);
+        self.fill_rect(Rect::new(x, rect.bottom() - 1, w, 1), color);
+        self.fill_rect(Rect::new(x, y, 1, h), color);
+        self.fill_rect(Rect::new(rect.right() - 1, y, 1, h), color);
+    }
+    fn draw_text(&mut self, font: &Font, color: Color, text: &str, pos: (i32, i32), align: Align);
+    // Copies the src part of a sprite sheet into dst (scaling if the sizes differ). Sheets never
+    // change, backends may keep them uploaded under key (one of the *_KEY constants)
+    fn blit_sprite(&mut self, key: u64, sheet: &SurfaceRef, src: Rect, dst: Rect);
+    // Copies many parts of the same sprite sheet at once, (src, dst) pairs
+    fn blit_batch(&mut self, key: u64, sheet: &SurfaceRef, blits: &[(Rect, Rect)]) {
+        for (src, dst) in blits {
+            self.blit_sprite(key, sheet, *src, *dst);
+        }
+    }
+    // Same as blit_sprite for images that rarely change, backends may keep them uploaded under
+    // key until the version changes
+    fn blit_cached(&mut self, key: u64, _version: u32, image: &SurfaceRef, src: Rect, dst: Rect) {
+        self.blit_sprite(key, image, src, dst);
+    }
+    // Same as blit_batch, but multiplies the sprites' colors by a tint per blit
+    fn blit_tinted(&mut self, key: u64, sheet: &SurfaceRef, blits: &[(Rect, Rect, Color)]);
+    // Shows the finished frame
+    fn present(&mut self);
+}
+
+pub const ATLAS_KEY: u64 = 0; //Key of the tile atlas sheet (sheet keys are separate from image keys)
+pub const GLYPHS_KEY: u64 = 1; //Key of the glyph sheet
+
+// Gets the rect text of the given size is drawn in, pos is the anchor picked by align
+pub fn align_rect(pos: (i32, i32), w: u32, h: u32, align: Align) -> Rect {
+    match align {
+        Align::LEFT => Rect::new(pos.0, pos.1, w, h),
+        Align::CENTER => Rect::new(pos.0 - w as i32 / 2, pos.1 - h as i32 / 2, w, h),
+    }
+}
+
+// Draws to an SDL canvas (a window or a surface), keeping the textures of rendered text around
+// between frames since the texture creator outlives them
+pub struct CanvasBackend<'t, T: RenderTarget> {
+    pub canvas: Canvas<T>,
+    texture_creator: &'t TextureCreator<T::Context>,
+    text: TextCache<Texture<'t>>,
+    images: HashMap<u64, (u32, Texture<'t>)>, //Uploaded images by key, with their version
+    sheets: HashMap<u64, Texture<'t>>,        //Uploaded sprite sheets by key
+}
+
+// Gets the texture of a sprite sheet, uploading it the first time it's drawn
+fn upload_sheet<'a, 't, C>(
+    sheets: &'a mut HashMap<u64, Texture<'t>>,
+    texture_creator: &'t TextureCreator<C>,
+    key: u64,
+    sheet: &SurfaceRef,
+) -> Option<&'a mut Texture<'t>> {
+    match sheets.entry(key) {
+        Entry::Occupied(e) => Some(e.into_mut()),
+        Entry::Vacant(e) => {
+            Some(e.insert(texture_creator.create_texture_from_surface(sheet).ok()?))
+        }
+    }
+}
+
+impl<'t, T: RenderTarget> CanvasBackend<'t, T> {
+    pub fn new(canvas: Canvas<T>, texture_creator: &'t TextureCreator<T::Context>) -> Self {
+        Self {
+            canvas: canvas,
+            texture_creator: texture_creator,
+            text: TextCache::new(TEXT_CACHE_SIZE),
+            images: HashMap::new(),
+            sheets: HashMap::new(),
+        }
+    }
+}
+
+impl<'t> CanvasBackend<'t, Window> {
+    // Picks how the game is drawn into the window, at a fixed logical size scaled to fit (SDL
+    // letterboxes it and maps the mouse into it) or at the window's size. On high DPI screens
+    // the window has more pixels than its size says, drawing is scaled up to match so the game
+    // isn't tiny. Called again whenever the window changes size
+    pub fn fit_window(&mut self, logical: Option<(u32, u32)>) {
+        match logical {
+            Some((w, h)) => {
+                let _ = self.canvas.set_logical_size(w, h);
+            }
+            None => {
+                // a logical size of 0 turns it off
+                let _ = self.canvas.set_logical_size(0, 0);
+                let (pixels, _) = self.canvas.output_size().unwrap_or((1, 1));
+                let (points, _) = self.canvas.window().size();
+                let scale = pixels as f32 / points.max(1) as f32;
+                let _ = self.canvas.set_scale(scale, scale);
+            }
+        }
+    }
+
+    // Fullscreen at the desktop's resolution, the window keeps its size for when it's turned off
+    pub fn set_fullscreen(&mut self, on: bool) {
+        let mode = if on {
+            FullscreenType::Desktop
+        } else {
+            FullscreenType::Off
+        };
+        if let Err(e) = self.canvas.window_mut().set_fullscreen(mode) {
+            println!("Could not change fullscreen: {}", e);
+        }
+    }
+}
+
+impl<'t, T: RenderTarget> RenderBackend for CanvasBackend<'t, T> {
+    // In drawing coordinates, so the logical size or the output size without the DPI scale
+    fn size(&self) -> (u32, u32) {
+        let (lw, lh) = self.canvas.logical_size();
+        if lw > 0 && lh > 0 {
+            return (lw, lh);
+        }
+        let (w, h) = self.canvas.output_size().unwrap();
+        let (sx, sy) = self.canvas.scale();
+        ((w as f32 / sx) as u32, (h as f32 / sy) as u32)
+    }
+
+    fn fill_rect(&mut self, rect: Rect, color: Color) {
+        if color.a < 255 {
+            self.canvas.set_blend_mode(BlendMode::Blend);
+        }
+        self.canvas.set_draw_color(color);
+        let _ = self.canvas.fill_rect(rect);
+        self.canvas.set_blend_mode(BlendMode::None);
+    }
+
+    fn draw_text(&mut self, font: &Font, color: Color, text: &str, pos: (i32, i32), align: Align) {
+        if text.is_empty() {
+            return;
+        }
+        let texture_creator = self.texture_creator;
+        let texture = self
+            .text
+            .get_or_render(TextKey::new(font, color, text), || {
+                let surface = font.render(text).blended(color).ok()?;
+                texture_creator.create_texture_from_surface(&surface).ok()
+            });
+        if let Some(texture) = texture {
+            let query = texture.query();
+            let target = align_rect(pos, query.width, query.height, align);
+            let _ = self.canvas.copy(texture, None, target);
+        }
+    }
+
+    fn blit_sprite(&mut self, key: u64, sheet: &SurfaceRef, src: Rect, dst: Rect) {
+        self.blit_batch(key, sheet, &[(src, dst)]);
+    }
+
+    fn blit_batch(&mut self, key: u64, sheet: &SurfaceRef, blits: &[(Rect, Rect)]) {
+        let texture = match upload_sheet(&mut self.sheets, self.texture_creator, key, sheet) {
+            Some(t) => t,
+            None => return,
+        };
+        for (src, dst) in blits {
+            let _ = self.canvas.copy(texture, *src, *dst);
+        }
+    }
+
+    fn blit_cached(&mut self, key: u64, version: u32, image: &SurfaceRef, src: Rect, dst: Rect) {
+        let stale = self.images.get(&key).is_none_or(|(v, _)| *v != version);
+        if stale {
+            let mut texture = match self.texture_creator.create_texture_from_surface(image) {
+                Ok(t) => t,
+                Err(_) => return,
+            };
+            texture.set_blend_mode(BlendMode::Blend);
+            self.images.insert(key, (version, texture));
+        }
+        let (_, texture) = self.images.get(&key).unwrap();
+        let _ = self.canvas.copy(texture, src, dst);
+    }
+
+    fn blit_tinted(&mut self, key: u64, sheet: &SurfaceRef, blits: &[(Rect, Rect, Color)]) {
+        let texture = match upload_sheet(&mut self.sheets, self.texture_creator, key, sheet) {
+            Some(t) => t,
+            None => return,
+        };
+        texture.set_blend_mode(BlendMode::Blend);
+        for (src, dst, tint) in blits {
+            texture.set_color_mod(tint.r, tint.g, tint.b);
+            let _ = self.canvas.copy(texture, *src, *dst);
+        }
+        texture.set_color_mod(255, 255, 255);
+    }
+
+    fn present(&mut self) {
+        self.canvas.present();
+        // the letterbox bars are outside of anything the game draws, clear them for the next frame
+        self.canvas.set_draw_color(Color::BLACK);
+        self.canvas.clear();
+    }
+}
+
+//Plain block of pixels in memory, lets rendering run without a window or video driver
+pub struct Framebuffer {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<Color>, //Row by row
+    pub frames: u32,        //Number of presented frames
+    text: TextCache<Surface<'static>>,
+}
+
+impl Framebuffer {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width: width,
+            height: height,
+            pixels: vec![Color::RGB(0, 0, 0); (width * height) as usize],
+            frames: 0,
+            text: TextCache::new(TEXT_CACHE_SIZE),
+        }
+    }
+
+    pub fn get_pixel(&self, x: u32, y: u32) -> Color {
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    // Blends a color over one pixel (ignores pixels outside the buffer)
+    fn blend_pixel(&mut self, x: i32, y: i32, color: Color) {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return;
+        }
+        let i = (y as u32 * self.width + x as u32) as usize;
+        if color.a == 255 {
+            self.pixels[i] = color;
+            return;
+        }
+        let old = self.pixels[i];
+        let a = color.a as u32;
+        let mix = |new: u8, old: u8| ((new as u32 * a + old as u32 * (255 - a)) / 255) as u8;
+        self.pixels[i] = Color::RGB(
+            mix(color.r, old.r),
+            mix(color.g, old.g),
+            mix(color.b, old.b),
+        );
+    }
+
+    // Blends the src part of a surface into dst, sampling the nearest pixel when scaling
+    fn blit_surface(&mut self, surface: &SurfaceRef, src: Rect, dst: Rect, tint: Color) {
+        let rgba = surface.convert_format(PixelFormatEnum::RGBA32).unwrap();
+        let pitch = rgba.pitch() as usize;
+        let tinted = |c: u8, t: u8| (c as u32 * t as u32 / 255) as u8;
+        rgba.with_lock(|data| {
+            for dy in 0..dst.height() {
+                for dx in 0..dst.width() {
+                    let sx = src.x() as u32 + dx * src.width() / dst.width();
+                    let sy = src.y() as u32 + dy * src.height() / dst.height();
+                    if sx >= rgba.width() || sy >= rgba.height() {
+                        continue;
+                    }
+                    let i = sy as usize * pitch + sx as usize * 4;
+                    let color = Color::RGBA(
+                        tinted(data[i], tint.r),
+                        tinted(data[i + 1], tint.g),
+                        tinted(data[i + 2], tint.b),
+                        data[i + 3],
+                    );
+                    self.blend_pixel(dst.x() + dx as i32, dst.y() + dy as i32, color);
+                }
+            }
+        });
+    }
+
+    // Copies the pixels into an SDL surface (used to save or compare frames)
+    pub fn to_surface(&self) -> Surface<'static> {
+        let mut surface = Surface::new(self.width, self.height, PixelFormatEnum::RGBA32).unwrap();
+        let pitch = surface.pitch() as usize;
+        surface.with_lock_mut(|data| {
+            for y in 0..self.height {
+                for x in 0..self.width {
+                    let c = self.get_pixel(x, y);
+                    let i = y as usize * pitch + x as usize * 4;
+                    data[i..i + 4].copy_from_slice(&[c.r, c.g, c.b, 255]);
+                }
+            }
+        });
+        surface
+    }
+
+    pub fn save_png(&self, path: &std::path::Path) -> Result<(), String> {
+        self.to_surface().save(path)
+    }
+}
+
+impl RenderBackend for Framebuffer {
+    fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn fill_rect(&mut self, rect: Rect, color: Color) {
+        let rect = match rect.intersection(Rect::new(0, 0, self.width, self.height)) {
+            Some(r) => r,
+            None => return,
+        };
+        for y in rect.y()..rect.y() + rect.height() as i32 {
+            for x in rect.x()..rect.x() + rect.width() as i32 {
+                self.blend_pixel(x, y, color);
+            }
+        }
+    }
+
+    fn draw_text(&mut self, font: &Font, color: Color, text: &str, pos: (i32, i32), align: Align) {
+        if text.is_empty() {
+            return;
+        }
+        // the cache is moved out while blitting since that needs the framebuffer mutably
+        let mut cache = std::mem::replace(&mut self.text, TextCache::new(0));
+        let key = TextKey::new(font, color, text);
+        if let Some(surface) = cache.get_or_render(key, || font.render(text).blended(color).ok()) {
+            let target = align_rect(pos, surface.width(), surface.height(), align);
+            self.blit_surface(surface, surface.rect(), target, Color::WHITE);
+        }
+        self.text = cache;
+    }
+
+    fn blit_sprite(&mut self, _key: u64, sheet: &SurfaceRef, src: Rect, dst: Rect) {
+        self.blit_surface(sheet, src, dst, Color::WHITE);
+    }
+
+    fn blit_tinted(&mut self, _key: u64, sheet: &SurfaceRef, blits: &[(Rect, Rect, Color)]) {
+        for (src, dst, tint) in blits {
+            self.blit_surface(sheet, *src, *dst, *tint);
+        }
+    }
+
+    fn present(&mut self) {
+        self.frames += 1;
+    }
+}
@@ -0,0 +1,119 @@
+use rand::Rng;
+use sdl2::rect::Rect;
+
+pub const MIN_ZOOM: f64 = 0.5;
+pub const MAX_ZOOM: f64 = 3.0;
+const ZOOM_STEP: f64 = 1.1; //Zoom multiplier for one mouse wheel notch
+
+//What part of the world is on screen, everything converting between screen and world coords goes through here
+pub struct Camera {
+    pub pos: (f64, f64), //World position shown at the center of the viewport
+    pub zoom: f64,       //Screen pixels per world unit
+    pub viewport: Rect,  //Part of the screen the world is drawn in
+    pub follow: f64, //Fraction of the distance to the target covered every tick (1.0 sticks to it)
+    shake: f64,      //Current shake strength in screen pixels
+    shake_ticks: i32, //Ticks of shaking left
+    shake_offset: (f64, f64),
+}
+
+impl Camera {
+    pub fn new(viewport: Rect) -> Self {
+        Self {
+            pos: (0.0, 0.0),
+            zoom: 1.0,
+            viewport: viewport,
+            follow: 0.2,
+            shake: 0.0,
+            shake_ticks: 0,
+            shake_offset: (0.0, 0.0),
+        }
+    }
+
+    // Center of the viewport on screen
+    fn center(&self) -> (f64, f64) {
+        (
+            self.viewport.x() as f64 + self.viewport.width() as f64 / 2.0,
+            self.viewport.y() as f64 + self.viewport.height() as f64 / 2.0,
+        )
+    }
+
+    pub fn world_to_screen(&self, pos: (f64, f64)) -> (f64, f64) {
+        let c = self.center();
+        (
+            (pos.0 - self.pos.0) * self.zoom + c.0 + self.shake_offset.0,
+            (pos.1 - self.pos.1) * self.zoom + c.1 + self.shake_offset.1,
+        )
+    }
+
+    pub fn screen_to_world(&self, pos: (i32, i32)) -> (f64, f64) {
+        let c = self.center();
+        (
+            (pos.0 as f64 - c.0 - self.shake_offset.0) / self.zoom + self.pos.0,
+            (pos.1 as f64 - c.1 - self.shake_offset.1) / self.zoom + self.pos.1,
+        )
+    }
+
+    // Gets the tile under a screen position
+    pub fn screen_to_tile(&self, pos: (i32, i32), tile_size: i32) -> (i32, i32) {
+        let w = self.screen_to_world(pos);
+        (
+            (w.0 / tile_size as f64).floor() as i32,
+            (w.1 / tile_size as f64).floor() as i32,
+        )
+    }
+
+    // Gets the screen rect of a world space box (given by its top left corner and size)
+    pub fn rect_to_screen(&self, pos: (f64, f64), size: (f64, f64)) -> Rect {
+        let a = self.world_to_screen(pos);
+        let b = self.world_to_screen((pos.0 + size.0, pos.1 + size.1));
+        // round both corners so neighbouring rects share an edge without gaps
+        let (x0, y0) = (a.0.floor() as i32, a.1.floor() as i32);
+        let (x1, y1) = (b.0.floor() as i32, b.1.floor() as i32);
+        Rect::new(x0, y0, (x1 - x0).max(1) as u32, (y1 - y0).max(1) as u32)
+    }
+
+    // Gets the range of tiles that are (at least partially) visible, inclusive
+    pub fn visible_tiles(&self, tile_size: i32) -> ((i32, i32), (i32, i32)) {
+        let v = self.viewport;
+        let a = self.screen_to_tile((v.x(), v.y()), tile_size);
+        let b = self.screen_to_tile((v.right(), v.bottom()), tile_size);
+        ((a.0, b.0), (a.1, b.1))
+    }
+
+    // Moves the camera straight to a position (used when teleporting or loading)
+    pub fn snap(&mut self, target: (f64, f64)) {
+        self.pos = target;
+    }
+
+    // Moves the camera towards its target and updates the shake, once per tick
+    pub fn update(&mut self, target: (f64, f64)) {
+        self.pos.0 += (target.0 - self.pos.0) * self.follow;
+        self.pos.1 += (target.1 - self.pos.1) * self.follow;
+
+        if self.shake_ticks > 0 {
+            self.shake_ticks -= 1;
+            let mut rng = rand::thread_rng();
+            self.shake_offset = (
+                rng.gen_range(-1.0..1.0) * self.shake,
+                rng.gen_range(-1.0..1.0) * self.shake,
+            );
+            self.shake *= 0.85;
+        } else {
+            self.shake = 0.0;
+            self.shake_offset = (0.0, 0.0);
+        }
+    }
+
+    // Shakes the view for a number of ticks (stronger shakes override weaker ones)
+    pub fn shake(&mut self, strength: f64, ticks: i32) {
+        if strength >= self.shake {
+            self.shake = strength;
+            self.shake_ticks = ticks;
+        }
+    }
+
+    // Zooms in (positive) or out (negative) by mouse wheel notches
+    pub fn zoom_by(&mut self, notches: i32) {
+        self.zoom = (self.zoom * ZOOM_STEP.powi(notches)).clamp(MIN_ZOOM, MAX_ZOOM);
+    }
+}
@@ -0,0 +1,67 @@
+use sdl2::{
+    pixels::{Color, PixelFormatEnum},
+    rect::Rect,
+    render::BlendMode,
+    surface::Surface,
+    ttf::Font,
+};
+use std::collections::HashMap;
+
+use crate::world::world::Tile;
+
+//Every printable ascii character rendered once in white, tinted when drawn
+pub struct GlyphAtlas {
+    pub sheet: Surface<'static>,
+    pub glyphs: HashMap<char, Rect>,
+}
+
+impl GlyphAtlas {
+    pub fn new(font: &Font) -> Self {
+        let chars: Vec<char> = (' '..='~').collect();
+        let mut rendered = Vec::new();
+        let mut cell = (1, 1); //Size of the largest glyph
+        for c in chars.iter() {
+            if *c == ' ' {
+                continue;
+            }
+            let mut glyph = font.render_char(*c).blended(Color::WHITE).unwrap();
+            glyph.set_blend_mode(BlendMode::None).unwrap();
+            cell = (cell.0.max(glyph.width()), cell.1.max(glyph.height()));
+            rendered.push((*c, glyph));
+        }
+
+        // one cell per character in a single row
+        let mut sheet = Surface::new(
+            cell.0 * rendered.len() as u32,
+            cell.1,
+            PixelFormatEnum::RGBA32,
+        )
+        .unwrap();
+        let mut glyphs = HashMap::new();
+        for (i, (c, glyph)) in rendered.iter().enumerate() {
+            let dst = Rect::new((i as u32 * cell.0) as i32, 0, glyph.width(), glyph.height());
+            glyph.blit(None, &mut sheet, dst).unwrap();
+            glyphs.insert(*c, dst);
+        }
+        Self {
+            sheet: sheet,
+            glyphs: glyphs,
+        }
+    }
+
+    // Gets where to draw a glyph so it fits centered inside a rect, keeping its aspect ratio
+    pub fn fit(&self, c: char, rect: Rect) -> Option<(Rect, Rect)> {
+        let src = *self.glyphs.get(&c).or(self.glyphs.get(&'?'))?;
+        let h = rect.height() * 4 / 5;
+        let w = (h * src.width() / src.height().max(1)).max(1);
+        let dst = Rect::from_center(rect.center(), w, h.max(1));
+        Some((src, dst))
+    }
+}
+
+// Gets the character, foreground and background color a tile is shown with in glyph mode
+// (also used by the terminal frontend)
+pub fn tile_glyph(t: &Tile) -> (char, Color, Color) {
+    let bg = Color::RGB(t.color.r / 4, t.color.g / 4, t.color.b / 4);
+    (t.symbol, t.color, bg)
+}
@@ -0,0 +1,233 @@
+use sdl2::{
+    pixels::{Color, PixelFormatEnum},
+    rect::Rect,
+    surface::Surface,
+};
+use std::collections::HashMap;
+
+use crate::renderer::backend::RenderBackend;
+use crate::world::world::{chunk_of, World, CHUNK_SIZE};
+
+const MINIMAP_SIZE: u32 = 160; //Width and height of the minimap on screen
+const MINIMAP_SCALE: f64 = 2.0; //Minimap pixels per tile
+const MIN_MAP_SCALE: f64 = 0.5;
+const MAX_MAP_SCALE: f64 = 16.0;
+const MAP_ZOOM_STEP: f64 = 1.25; //Map scale multiplier for one mouse wheel notch
+
+//Low resolution picture of a chunk, one pixel per tile
+struct ChunkImage {
+    surface: Surface<'static>,
+    version: u32, //Changes every time the picture is redrawn, so backends upload it again
+}
+
+//Minimap in the corner of the screen and the full screen world map, both drawn from chunk images
+pub struct MapView {
+    pub open: bool,         //If the full screen map is shown
+    pub center: (f64, f64), //Tile at the center of the full screen map
+    pub scale: f64,         //Screen pixels per tile on the full screen map
+    chunks: HashMap<(i32, i32), ChunkImage>,
+    next_version: u32, //Never reused, not even after clear, so no stale picture is shown
+}
+
+// Key the image of a chunk is kept under by the backend
+fn chunk_key(chunk: (i32, i32)) -> u64 {
+    ((chunk.0 as u32 as u64) << 32) | chunk.1 as u32 as u64
+}
+
+impl MapView {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            center: (0.0, 0.0),
+            scale: 4.0,
+            chunks: HashMap::new(),
+            next_version: 0,
+        }
+    }
+
+    // Opens or closes the full screen map, it always opens centered on the player
+    pub fn toggle(&mut self, player_tile: (f64, f64)) {
+        self.open = !self.open;
+        if self.open {
+            self.center = player_tile;
+        }
+    }
+
+    // Moves the full screen map by a distance dragged on screen
+    pub fn pan(&mut self, d: (i32, i32)) {
+        self.center.0 -= d.0 as f64 / self.scale;
+        self.center.1 -= d.1 as f64 / self.scale;
+    }
+
+    // Zooms the full screen map in (positive) or out (negative) by mouse wheel notches
+    pub fn zoom_by(&mut self, notches: i32) {
+        self.scale = (self.scale * MAP_ZOOM_STEP.powi(notches)).clamp(MIN_MAP_SCALE, MAX_MAP_SCALE);
+    }
+
+    // Forgets every chunk picture and closes the map (when another world is opened)
+    pub fn clear(&mut self) {
+        self.chunks.clear();
+        self.open = false;
+    }
+
+    // Redraws the pictures of chunks whose tiles changed
+    pub fn update(&mut self, world: &mut World) {
+        let changed: Vec<(i32, i32)> = world.changed_chunks.drain().collect();
+        for chunk in changed {
+            let surface = chunk_surface(world, chunk);
+            let version = self.next_version;
+            self.next_version += 1;
+            self.chunks.insert(
+                chunk,
+                ChunkImage {
+                    surface: surface,
+                    version: version,
+                },
+            );
+        }
+    }
+
+    // Draws the tiles around center (in tiles) into area at scale pixels per tile
+    fn draw_area(
+        &self,
+        canvas: &mut dyn RenderBackend,
+        area: Rect,
+        center: (f64, f64),
+        scale: f64,
+    ) {
+        let left = center.0 - area.width() as f64 / 2.0 / scale;
+        let top = center.1 - area.height() as f64 / 2.0 / scale;
+        // tiles covered by the area, end exclusive
+        let x = (
+            left.floor() as i32,
+            (left + area.width() as f64 / scale).ceil() as i32,
+        );
+        let y = (
+            top.floor() as i32,
+            (top + area.height() as f64 / scale).ceil() as i32,
+        );
+        let to_screen = |t: i32, start: f64, from: i32, to: i32| {
+            (from + ((t as f64 - start) * scale).floor() as i32).clamp(from, to)
+        };
+
+        let first = chunk_of((x.0, y.0));
+        let last = chunk_of((x.1 - 1, y.1 - 1));
+        for cy in first.1..=last.1 {
+            for cx in first.0..=last.0 {
+                let image = match self.chunks.get(&(cx, cy)) {
+                    Some(i) => i,
+                    None => continue,
+                };
+                // part of the chunk inside the area, in tiles
+                let (ox, oy) = (cx * CHUNK_SIZE, cy * CHUNK_SIZE);
+                let (sx0, sx1) = (ox.max(x.0), (ox + CHUNK_SIZE).min(x.1));
+                let (sy0, sy1) = (oy.max(y.0), (oy + CHUNK_SIZE).min(y.1));
+                if sx0 >= sx1 || sy0 >= sy1 {
+                    continue;
+                }
+                let src = Rect::new(sx0 - ox, sy0 - oy, (sx1 - sx0) as u32, (sy1 - sy0) as u32);
+                let dx0 = to_screen(sx0, left, area.x(), area.right());
+                let dx1 = to_screen(sx1, left, area.x(), area.right());
+                let dy0 = to_screen(sy0, top, area.y(), area.bottom());
+                let dy1 = to_screen(sy1, top, area.y(), area.bottom());
+                if dx0 >= dx1 || dy0 >= dy1 {
+                    continue;
+                }
+                let dst = Rect::new(dx0, dy0, (dx1 - dx0) as u32, (dy1 - dy0) as u32);
+                canvas.blit_cached(chunk_key((cx, cy)), image.version, &image.surface, src, dst);
+            }
+        }
+    }
+
+    // Draws a marker for the player, if it is inside the area
+    fn draw_player(
+        &self,
+        canvas: &mut dyn RenderBackend,
+        area: Rect,
+        center: (f64, f64),
+        scale: f64,
+        player: (f64, f64),
+    ) {
+        let p = (
+            area.x() as f64 + area.width() as f64 / 2.0 + (player.0 - center.0) * scale,
+            area.y() as f64 + area.height() as f64 / 2.0 + (player.1 - center.1) * scale,
+        );
+        if !area.contains_point((p.0 as i32, p.1 as i32)) {
+            return;
+        }
+        canvas.fill_rect(
+            Rect::from_center((p.0 as i32, p.1 as i32), 6, 6),
+            Color::BLACK,
+        );
+        canvas.fill_rect(
+            Rect::from_center((p.0 as i32, p.1 as i32), 4, 4),
+            Color::WHITE,
+        );
+    }
+
+    // Draws the area around the player in the top right corner of the screen
+    pub fn draw_minimap(&self, canvas: &mut dyn RenderBackend, world: &World, tile_size: i32) {
+        let (w, _) = canvas.size();
+        let area = Rect::new(
+            w as i32 - MINIMAP_SIZE as i32 - 10,
+            10,
+            MINIMAP_SIZE,
+            MINIMAP_SIZE,
+        );
+        let player = player_tile(world, tile_size);
+        canvas.fill_rect(
+            Rect::new(
+                area.x() - 2,
+                area.y() - 2,
+                area.width() + 4,
+                area.height() + 4,
+            ),
+            Color::RGBA(0, 0, 0, 200),
+        );
+        self.draw_area(canvas, area, player, MINIMAP_SCALE);
+        self.draw_player(canvas, area, player, MINIMAP_SCALE, player);
+    }
+
+    // Draws the full screen map
+    pub fn draw_map(&self, canvas: &mut dyn RenderBackend, world: &World, tile_size: i32) {
+        let (w, h) = canvas.size();
+        let area = Rect::new(0, 0, w, h);
+        canvas.fill_rect(area, Color::RGB(10, 10, 20));
+        self.draw_area(canvas, area, self.center, self.scale);
+        self.draw_player(
+            canvas,
+            area,
+            self.center,
+            self.scale,
+            player_tile(world, tile_size),
+        );
+    }
+}
+
+// Position of the player in tiles
+pub fn player_tile(world: &World, tile_size: i32) -> (f64, f64) {
+    let p = world.player.pos;
+    (p.0 / tile_size as f64, p.1 / tile_size as f64)
+}
+
+// Draws one pixel per tile of a chunk in the tile's color, tiles the player hasn't explored are left out
+fn chunk_surface(world: &World, chunk: (i32, i32)) -> Surface<'static> {
+    let size = CHUNK_SIZE as u32;
+    let mut surface = Surface::new(size, size, PixelFormatEnum::RGBA32).unwrap();
+    let pitch = surface.pitch() as usize;
+    surface.with_lock_mut(|data| {
+        for y in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                let pos = (chunk.0 * CHUNK_SIZE + x, chunk.1 * CHUNK_SIZE + y);
+                let tile = world.world.get(&pos).and_then(|id| world.tiles.get(id));
+                let color = match tile {
+                    Some(t) if world.player.explored.contains(pos) => t.color,
+                    _ => Color::RGBA(0, 0, 0, 0),
+                };
+                let i = y as usize * pitch + x as usize * 4;
+                data[i..i + 4].copy_from_slice(&[color.r, color.g, color.b, color.a]);
+            }
+        }
+    });
+    surface
+}
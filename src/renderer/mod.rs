@@ -1 +1,9 @@
-pub mod renderer;
\ No newline at end of file
+pub mod atlas;
+pub mod autotile;
+pub mod backend;
+pub mod camera;
+pub mod glyphs;
+pub mod map;
+pub mod renderer;
+pub mod snapshot;
+pub mod text_cache;
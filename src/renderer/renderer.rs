@@ -1,167 +1,421 @@
-use std::path::Path;
-
-use sdl2::{
-    pixels::Color,
-    rect::Rect,
-    render::{Canvas, TextureCreator},
-    ttf::Font,
-    video::{Window, WindowContext},
-};
+use sdl2::{pixels::Color, rect::Rect, ttf::Font};
 
-use crate::gui::gui::Gui;
-use crate::{
-    gui::gui::Gui_Window,
-    world::world::{Tile, World},
+use crate::entity::entity::Entity;
+use crate::light::light::MAX_LIGHT;
+use crate::gui::{
+    gui::Gui,
+    hud::Hud,
+    widget::{Widget, WidgetKind},
+};
+use crate::renderer::{
+    atlas::Atlas,
+    autotile::{edge_masks, edge_rect, SIDES},
+    backend::{RenderBackend, ATLAS_KEY, GLYPHS_KEY},
+    camera::Camera,
+    glyphs::{tile_glyph, GlyphAtlas},
+    map::MapView,
 };
+use crate::world::world::{chunk_of, Tile, World, CHUNK_SIZE};
+
+const DARKEST: u8 = 220; //Opacity of the darkness over tiles without any light
+
+// How the world is drawn, switched at runtime with F2
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum RenderMode {
+    Sprites, //Tile atlas (or flat colors)
+    Glyphs,  //Tile symbols over a tinted background
+}
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum Align {
     LEFT,
     CENTER,
-    RIGHT,
 }
 
 pub struct Renderer {
     pub screen_area: Rect,  //Rect that stores screen height and width
     pub clear_color: Color, //Color on clear (set to black anyway)
-    pub tile_size: i32,     //Size of tile rendering on screen
+    pub tile_size: i32,     //Size of a tile in world units (on screen at zoom 1)
+    pub camera: Camera,
+    pub atlas: Option<Atlas>, //Tile sprites, tiles are drawn as flat colors without it
+    pub mode: RenderMode,
+    glyphs: Option<GlyphAtlas>, //Built from the font the first time glyph mode is drawn
+    pub map: MapView,           //Minimap and full screen map
     pub gui: Gui,
+    pub hud: Hud,
+    pub target: Option<(i32, i32)>, //Tile picked with the controller cursor, outlined when set
 }
 
 impl Renderer {
     pub fn new(width: u32, height: u32) -> Self {
         let mut gui = Gui::new();
-        gui.draw_stack
-            .push(Gui_Window::new((0, 0), "".to_string(), Align::LEFT));
+        let mut hover = Widget::label("hover_tile", "");
+        if let WidgetKind::Label { color, .. } = &mut hover.kind {
+            *color = Color::BLACK;
+        }
+        gui.add(hover);
+        let hud = Hud::new(&mut gui);
 
         Self {
             screen_area: Rect::new(0, 0, width, height),
             clear_color: Color::RGB(0, 0, 0),
             tile_size: 50,
+            camera: Camera::new(Rect::new(0, 0, width, height)),
+            atlas: None,
+            mode: RenderMode::Sprites,
+            glyphs: None,
+            map: MapView::new(),
             gui: gui,
+            hud: hud,
+            target: None,
         }
     }
 
+    // Follows the window to a new size, the world view fills all of it
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.screen_area = Rect::new(0, 0, width, height);
+        self.camera.viewport = Rect::new(0, 0, width, height);
+    }
+
     // Clears the canvas
-    pub fn clear(&self, canvas: &mut Canvas<Window>) {
-        canvas.set_draw_color(self.clear_color);
-        let _ = canvas.fill_rect(self.screen_area);
+    pub fn clear(&self, canvas: &mut dyn RenderBackend) {
+        canvas.fill_rect(self.screen_area, self.clear_color);
     }
 
-    pub fn draw_text(
-        &self,
-        canvas: &mut Canvas<Window>,
-        font: &sdl2::ttf::Font,
-        color: Color,
-        text: String,
-        pos: (i32, i32),
-        align: Align,
-    ) {
-        let surface = font.render(&text).blended(color).unwrap();
-        let texture_creator = canvas.texture_creator();
-        let texture = texture_creator
-            .create_texture_from_surface(&surface)
-            .unwrap();
-        let target: Rect;
-        if align == Align::LEFT {
-            target = Rect::new(pos.0, pos.1, surface.width(), surface.height());
-        } else if align == Align::CENTER {
-            target = Rect::new(
-                pos.0 - surface.width() as i32 / 2,
-                pos.1 - surface.height() as i32 / 2,
-                surface.width(),
-                surface.height(),
-            );
-        } else {
-            target = Rect::new(
-                pos.0 - surface.width() as i32,
-                pos.1 - surface.height() as i32,
-                surface.width(),
-                surface.height(),
-            );
+    // Screen rect of the player
+    fn player_rect(&self, world: &World) -> Rect {
+        let p = world.player.pos;
+        let size = world.player.size;
+        self.camera.rect_to_screen(
+            (p.0 - size.0 as f64 / 2.0, p.1 - size.1 as f64 / 2.0),
+            (size.0 as f64, size.1 as f64),
+        )
+    }
+
+    // Screen rect of an entity, None if it is off screen
+    fn entity_rect(&self, world: &World, id: &Entity) -> Option<Rect> {
+        let pos = world.entities.positions.get(id).unwrap();
+        let size = world.entities.hitboxes.get(id).unwrap();
+        // dropped items bob up and down
+        let bob = match world.entities.items.get(id) {
+            Some(item) => (item.age as f64 * 0.15).sin() * 3.0,
+            None => 0.0,
+        };
+        let e_rect: Rect = self.camera.rect_to_screen(
+            (
+                pos.0 - size.0 as f64 / 2.0,
+                pos.1 - size.1 as f64 / 2.0 + bob,
+            ),
+            (size.0 as f64, size.1 as f64),
+        );
+        if !e_rect.has_intersection(self.screen_area) {
+            return None;
         }
+        // hidden by the fog of war
+        let tile = (
+            (pos.0 / self.tile_size as f64).floor() as i32,
+            (pos.1 / self.tile_size as f64).floor() as i32,
+        );
+        if !world.can_see(tile) {
+            return None;
+        }
+        Some(e_rect)
+    }
 
-        let _ = canvas.copy(&texture, None, target);
+    pub fn draw_player(&self, canvas: &mut dyn RenderBackend, world: &World) {
+        canvas.fill_rect(self.player_rect(world), world.player.color);
     }
 
-    pub fn draw_player(&self, canvas: &mut Canvas<Window>, world: &mut World) {
-        let sw = self.screen_area.width();
-        let sh = self.screen_area.height();
-        let p_rect: Rect = Rect::new(
-            (sw / 2 - world.player.size.0 / 2) as i32,
-            (sh / 2 - world.player.size.1 / 2) as i32,
-            world.player.size.0,
-            world.player.size.1,
-        );
-        canvas.set_draw_color(world.player.color);
-        let _ = canvas.fill_rect(p_rect);
+    // Draws every entity that is on screen
+    pub fn draw_entities(&self, canvas: &mut dyn RenderBackend, world: &World) {
+        for id in world.entities.alive.iter() {
+            let sprite = match world.entities.sprites.get(id) {
+                Some(s) => s,
+                None => continue,
+            };
+            if let Some(e_rect) = self.entity_rect(world, id) {
+                canvas.fill_rect(e_rect, sprite.color);
+            }
+        }
     }
 
-    // Note: Idk if this works right or not, did the calculations in my head
-    pub fn render(
-        &mut self,
-        canvas: &mut Canvas<Window>,
-        world: &mut World,
-        font: &sdl2::ttf::Font,
-        texture_creator: &TextureCreator<WindowContext>,
-        m_coords: (i32, i32),
-    ) {
-        self.clear(canvas);
+    // Draws the visible tiles from the atlas (or as flat colors and symbols without one)
+    fn draw_tiles(&self, canvas: &mut dyn RenderBackend, world: &World, font: &Font) {
+        let (x, y) = self.camera.visible_tiles(self.tile_size);
+        let ts = self.tile_size as f64;
+        let mut blits: Vec<(Rect, Rect)> = Vec::new();
+        let mut symbols: Vec<(char, Rect)> = Vec::new();
+        let mut edges: Vec<(Rect, Rect)> = Vec::new();
+        let mut strips: Vec<(Rect, Color)> = Vec::new(); //Edges without a sprite
+        for i in y.0..=y.1 {
+            for j in x.0..=x.1 {
+                let tid: i32 = *world.world.get(&(j, i)).unwrap();
+                let t: &Tile = world.tiles.get(&tid).unwrap();
+                let t_rect: Rect = self
+                    .camera
+                    .rect_to_screen((j as f64 * ts, i as f64 * ts), (ts, ts));
 
-        // For now, assume the world is centered at 0, 0 (later will be centered on player)
-        let sw = self.screen_area.width();
-        let sh = self.screen_area.height();
+                // sprite from the atlas if there is one, otherwise the flat color and symbol
+                let frame = match (&self.atlas, &t.sprite) {
+                    (Some(atlas), Some(sprite)) => atlas.frame(sprite),
+                    _ => None,
+                };
+                match frame {
+                    Some(src) => blits.push((src, t_rect)),
+                    None => {
+                        canvas.fill_rect(t_rect, t.color);
+                        if t.symbol != ' ' {
+                            symbols.push((t.symbol, t_rect));
+                        }
+                    }
+                }
 
-        // Todo: change this when implementing player struct
-        // Absolute position of player on the map
-        let p: (f64, f64) = world.player.pos;
+                // edges neighbouring tiles spill over this one
+                for (nid, mask) in edge_masks(&world.world, &world.tiles, (j, i)) {
+                    let n: &Tile = world.tiles.get(&nid).unwrap();
+                    for (bit, _, side) in SIDES.iter() {
+                        if mask & bit == 0 {
+                            continue;
+                        }
+                        let frame = match (&self.atlas, &n.sprite) {
+                            (Some(atlas), Some(sprite)) => {
+                                atlas.frame(&format!("{}_{}", sprite, side))
+                            }
+                            _ => None,
+                        };
+                        match frame {
+                            Some(src) => edges.push((src, t_rect)),
+                            None => strips.push((edge_rect(t_rect, *bit), n.color)),
+                        }
+                    }
+                }
+            }
+        }
+        if let Some(atlas) = &self.atlas {
+            canvas.blit_batch(ATLAS_KEY, &atlas.sheet, &blits);
+            canvas.blit_batch(ATLAS_KEY, &atlas.sheet, &edges);
+        }
+        for (rect, color) in strips {
+            canvas.fill_rect(rect, color);
+        }
+        for (symbol, rect) in symbols {
+            let center = rect.center();
+            canvas.draw_text(
+                font,
+                Color::BLACK,
+                &symbol.to_string(),
+                (center.x(), center.y()),
+                Align::CENTER,
+            );
+        }
+    }
 
-        // player position translated to tiles and rounded (what tile the player is currently on)
-        let pt: (i32, i32) = (
-            (p.0 as f64 / self.tile_size as f64).floor() as i32,
-            (p.1 as f64 / self.tile_size as f64).floor() as i32,
-        );
+    // Roguelike view: every tile, entity and the player drawn as a character
+    fn draw_glyphs(&mut self, canvas: &mut dyn RenderBackend, world: &World, font: &Font) {
+        if self.glyphs.is_none() {
+            self.glyphs = Some(GlyphAtlas::new(font));
+        }
+        let glyphs = self.glyphs.as_ref().unwrap();
 
-        // player relative position on the current player tile
-        let prt: (f64, f64) = (
-            ((pt.0 * self.tile_size) as f64 - p.0 as f64),
-            ((pt.1 * self.tile_size) as f64 - p.1 as f64),
-        );
+        let (x, y) = self.camera.visible_tiles(self.tile_size);
+        let ts = self.tile_size as f64;
+        let mut blits: Vec<(Rect, Rect, Color)> = Vec::new();
+        for i in y.0..=y.1 {
+            for j in x.0..=x.1 {
+                let tid: i32 = *world.world.get(&(j, i)).unwrap();
+                let t: &Tile = world.tiles.get(&tid).unwrap();
+                let t_rect: Rect = self
+                    .camera
+                    .rect_to_screen((j as f64 * ts, i as f64 * ts), (ts, ts));
+                let (symbol, fg, bg) = tile_glyph(t);
+                canvas.fill_rect(t_rect, bg);
+                if let Some((src, dst)) = glyphs.fit(symbol, t_rect) {
+                    blits.push((src, dst, fg));
+                }
+            }
+        }
 
-        // screen offset when rendering tiles
-        let so: (i32, i32) = ((prt.0.floor() as i32), (prt.1.floor() as i32));
+        // glyphs of entities and the player are drawn a tile high no matter the hitbox
+        let cell_size = (ts * self.camera.zoom).round().max(1.0) as u32;
+        for id in world.entities.alive.iter() {
+            let sprite = match world.entities.sprites.get(id) {
+                Some(s) => s,
+                None => continue,
+            };
+            let e_rect = match self.entity_rect(world, id) {
+                Some(r) => r,
+                None => continue,
+            };
+            let cell = Rect::from_center(e_rect.center(), cell_size, cell_size);
+            if let Some((src, dst)) = glyphs.fit(sprite.symbol, cell) {
+                blits.push((src, dst, sprite.color));
+            }
+        }
 
-        let hx = (sw as i32 / self.tile_size) / 2;
-        let hy = (sh as i32 / self.tile_size) / 2;
+        let p_rect = self.player_rect(world);
+        let cell = Rect::from_center(p_rect.center(), cell_size, cell_size);
+        if let Some((src, dst)) = glyphs.fit('@', cell) {
+            blits.push((src, dst, Color::WHITE));
+        }
+        canvas.blit_tinted(GLYPHS_KEY, &glyphs.sheet, &blits);
+    }
 
-        let x = (pt.0 - hx - 1, pt.0 + hx + 1); // tuple representing range of rendering in x direction
-        let y = (pt.1 - hy - 1, pt.1 + hy + 1); // tuple representing range of rendering in y direction
+    // Darkens every visible tile by how little light reaches it
+    fn draw_light(&self, canvas: &mut dyn RenderBackend, world: &World) {
+        let (x, y) = self.camera.visible_tiles(self.tile_size);
+        let ts = self.tile_size as f64;
+        for i in y.0..=y.1 {
+            for j in x.0..=x.1 {
+                let level = world.light_level((j, i));
+                if level >= MAX_LIGHT {
+                    continue;
+                }
+                let dark = (MAX_LIGHT - level) as u32 * DARKEST as u32 / MAX_LIGHT as u32;
+                let t_rect: Rect = self
+                    .camera
+                    .rect_to_screen((j as f64 * ts, i as f64 * ts), (ts, ts));
+                canvas.fill_rect(t_rect, Color::RGBA(5, 5, 20, dark as u8));
+            }
+        }
+    }
 
-        for i in y.0..y.1 {
-            for j in x.0..x.1 {
-                if !world.world.contains_key(&(j, i)) {
-                    world.generate_tile(j, i);
+    // Hides tiles the player hasn't explored and dims the ones out of sight in line of sight mode
+    fn draw_fog(&self, canvas: &mut dyn RenderBackend, world: &World) {
+        let (x, y) = self.camera.visible_tiles(self.tile_size);
+        let ts = self.tile_size as f64;
+        for i in y.0..=y.1 {
+            for j in x.0..=x.1 {
+                let color = if !world.player.explored.contains((j, i)) {
+                    Color::BLACK
+                } else if !world.can_see((j, i)) {
+                    Color::RGBA(0, 0, 0, 140)
+                } else {
+                    continue;
+                };
+                let t_rect: Rect = self
+                    .camera
+                    .rect_to_screen((j as f64 * ts, i as f64 * ts), (ts, ts));
+                canvas.fill_rect(t_rect, color);
+            }
+        }
+    }
+
+    // Outlines the tile the controller cursor is on
+    fn draw_target(&self, canvas: &mut dyn RenderBackend) {
+        if let Some(tile) = self.target {
+            let ts = self.tile_size as f64;
+            let rect = self
+                .camera
+                .rect_to_screen((tile.0 as f64 * ts, tile.1 as f64 * ts), (ts, ts));
+            canvas.draw_rect(rect, Color::WHITE);
+            let inner = Rect::new(
+                rect.x() + 1,
+                rect.y() + 1,
+                rect.width().saturating_sub(2),
+                rect.height().saturating_sub(2),
+            );
+            canvas.draw_rect(inner, Color::BLACK);
+        }
+    }
+
+    // Outlines chunks, collision boxes and solid tiles, each if it is switched on in the debug overlay
+    fn draw_debug_outlines(&self, canvas: &mut dyn RenderBackend, world: &World) {
+        let debug = &self.gui.debug;
+        let (x, y) = self.camera.visible_tiles(self.tile_size);
+        let ts = self.tile_size as f64;
+
+        if debug.solid_tiles {
+            for i in y.0..=y.1 {
+                for j in x.0..=x.1 {
+                    let solid = world
+                        .world
+                        .get(&(j, i))
+                        .and_then(|id| world.tiles.get(id))
+                        .is_some_and(|t| t.solid);
+                    if solid {
+                        let t_rect = self
+                            .camera
+                            .rect_to_screen((j as f64 * ts, i as f64 * ts), (ts, ts));
+                        canvas.draw_rect(t_rect, Color::RGB(60, 120, 255));
+                    }
                 }
-                let tid: i32 = world.world.get(&(j, i)).unwrap().clone();
-                let t: &Tile = world.tiles.get(&tid).unwrap();
-                let t_rect: Rect = Rect::new(
-                    so.0 + (j - x.0 - 1) * self.tile_size as i32,
-                    so.1 + (i - y.0 - 1) * self.tile_size as i32,
-                    self.tile_size as u32,
-                    self.tile_size as u32,
+            }
+        }
+
+        if debug.chunk_borders {
+            let cs = CHUNK_SIZE as f64 * ts;
+            let (first, last) = (chunk_of((x.0, y.0)), chunk_of((x.1, y.1)));
+            for cy in first.1..=last.1 {
+                for cx in first.0..=last.0 {
+                    let c_rect = self
+                        .camera
+                        .rect_to_screen((cx as f64 * cs, cy as f64 * cs), (cs, cs));
+                    canvas.draw_rect(c_rect, Color::RGB(255, 220, 0));
+                }
+            }
+        }
+
+        if debug.hitboxes {
+            canvas.draw_rect(self.player_rect(world), Color::RGB(255, 40, 40));
+            for id in world.entities.alive.iter() {
+                let pos = world.entities.positions.get(id).unwrap();
+                let size = world.entities.hitboxes.get(id).unwrap();
+                let e_rect = self.camera.rect_to_screen(
+                    (pos.0 - size.0 as f64 / 2.0, pos.1 - size.1 as f64 / 2.0),
+                    (size.0 as f64, size.1 as f64),
                 );
-                canvas.set_draw_color(t.color);
-                let _ = canvas.fill_rect(t_rect);
+                canvas.draw_rect(e_rect, Color::RGB(255, 40, 40));
             }
         }
+    }
+
+    // Draws the world and the gui over it, the caller presents the frame (so overlays can be
+    // drawn on top first)
+    pub fn render(
+        &mut self,
+        canvas: &mut dyn RenderBackend,
+        world: &mut World,
+        font: &Font,
+        m_coords: (i32, i32),
+    ) {
+        self.clear(canvas);
+
+        // make sure everything on screen exists before drawing
+        let (x, y) = self.camera.visible_tiles(self.tile_size);
+        world.gen((x.0, x.1 + 1), (y.0, y.1 + 1));
+        self.map.update(world);
+
+        if self.mode == RenderMode::Glyphs {
+            self.draw_glyphs(canvas, world, font);
+        } else {
+            self.draw_tiles(canvas, world, font);
+            self.draw_entities(canvas, world);
+            self.draw_player(canvas, world);
+        }
+        self.draw_light(canvas, world);
+        self.draw_fog(canvas, world);
+        self.draw_debug_outlines(canvas, world);
+        self.draw_target(canvas);
+        if self.map.open {
+            self.map.draw_map(canvas, world, self.tile_size);
+        } else {
+            self.map.draw_minimap(canvas, world, self.tile_size);
+        }
 
-        self.draw_player(canvas, world);
         let m_tile = world
-            .tiles
-            .get(&world.get_tile_id_from_rel(m_coords, &self))
-            .unwrap();
-        self.gui.draw_stack[0].text = m_tile.name.clone();
-        self.gui.draw_windows(&self, canvas, font);
-        canvas.present();
+            .get_tile_id_from_rel(m_coords, self)
+            .and_then(|id| world.tiles.get(&id));
+        self.gui.set_text("hover_tile", m_tile.map_or("", |t| t.name.as_str()));
+        self.hud.update(&mut self.gui, world);
+        self.gui.draw(canvas, font, self.atlas.as_ref());
+        self.gui.debug.tick();
+        self.gui.debug.draw(
+            canvas,
+            font,
+            world,
+            &self.camera,
+            self.tile_size,
+            m_coords,
+        );
     }
 }
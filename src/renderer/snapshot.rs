@@ -0,0 +1,197 @@
+use sdl2::{
+    image::LoadSurface,
+    pixels::{Color, PixelFormatEnum},
+    rwops::RWops,
+    surface::Surface,
+    ttf::{Font, Sdl2TtfContext},
+};
+use std::{fs, path::Path};
+
+use crate::player::player::Player;
+use crate::renderer::{atlas::Atlas, backend::Framebuffer, renderer::Renderer};
+use crate::world::world::World;
+
+// Golden image checks for Renderer::render, run by `cargo test` against the images in
+// assets/snapshots. `BLESS=1 cargo test snapshots` (or `cargo run -- --bless`) writes them,
+// after an intended change to rendering or to add a new snapshot. Snapshots without a golden
+// image are skipped
+
+pub const GOLDEN_DIR: &str = "assets/snapshots";
+pub const ATLAS: &str = "assets/textures/tiles.toml";
+// Built into the binary, so the text doesn't depend on where the game runs or its fonts
+const FONT: &[u8] = include_bytes!("../../assets/fonts/vcr_osd_mono.ttf");
+const FONT_SIZE: u16 = 32; //Whatever the settings say
+pub const OUTPUT_DIR: &str = "target/snapshots"; //Actual and diff images of failed snapshots
+
+const TOLERANCE: i32 = 8; //Max difference per color channel before a pixel counts as changed
+const MAX_CHANGED: f64 = 0.002; //Fraction of changed pixels allowed (font rendering differs a bit between machines)
+
+//One known view of the world
+struct Snapshot {
+    name: &'static str,
+    pos: (f64, f64),   //Player position
+    size: (u32, u32),  //Screen size
+    mouse: (i32, i32), //Mouse position (picks the hovered tile label)
+}
+
+const SNAPSHOTS: [Snapshot; 4] = [
+    Snapshot {
+        name: "spawn",
+        pos: (25.0, 25.0),
+        size: (800, 600),
+        mouse: (400, 300),
+    },
+    Snapshot {
+        name: "tile_corner",
+        pos: (100.0, 150.0),
+        size: (800, 600),
+        mouse: (10, 10),
+    },
+    Snapshot {
+        name: "negative_coords",
+        pos: (-1234.5, -987.25),
+        size: (800, 600),
+        mouse: (799, 599),
+    },
+    Snapshot {
+        name: "odd_size",
+        pos: (333.3, -77.7),
+        size: (555, 333),
+        mouse: (100, 200),
+    },
+];
+
+// Renders one snapshot into memory (a fresh world with the default seed, nothing else simulated)
+fn render_snapshot(snapshot: &Snapshot, font: &Font) -> Framebuffer {
+    let mut player = Player::new();
+    player.pos = snapshot.pos;
+    let mut world = World::new(player);
+    let mut renderer = Renderer::new(snapshot.size.0, snapshot.size.1);
+    renderer.camera.snap(snapshot.pos);
+    renderer.atlas = Atlas::load(Path::new(ATLAS)).ok();
+    // explore everything on screen so the fog of war doesn't hide what is being checked
+    let (x, y) = renderer.camera.visible_tiles(renderer.tile_size);
+    for i in y.0..=y.1 {
+        for j in x.0..=x.1 {
+            world.player.explored.insert((j, i));
+        }
+    }
+    let mut frame = Framebuffer::new(snapshot.size.0, snapshot.size.1);
+    renderer.render(&mut frame, &mut world, font, snapshot.mouse);
+    frame
+}
+
+// Compares a frame with a golden image, returns the number of changed pixels and an image showing them
+fn compare(frame: &Framebuffer, golden: &Surface) -> (u32, Framebuffer) {
+    let golden = golden.convert_format(PixelFormatEnum::RGBA32).unwrap();
+    let pitch = golden.pitch() as usize;
+    let mut diff = Framebuffer::new(frame.width, frame.height);
+    let mut changed = 0;
+    golden.with_lock(|data| {
+        for y in 0..frame.height {
+            for x in 0..frame.width {
+                let i = y as usize * pitch + x as usize * 4;
+                let g = Color::RGB(data[i], data[i + 1], data[i + 2]);
+                let a = frame.get_pixel(x, y);
+                let d = (a.r as i32 - g.r as i32)
+                    .abs()
+                    .max((a.g as i32 - g.g as i32).abs())
+                    .max((a.b as i32 - g.b as i32).abs());
+                let pixel = (y * frame.width + x) as usize;
+                if d > TOLERANCE {
+                    changed += 1;
+                    diff.pixels[pixel] = Color::RGB(255, 0, 0);
+                } else {
+                    // dimmed grey copy of the frame so changes stand out
+                    let grey = ((a.r as u32 + a.g as u32 + a.b as u32) / 12) as u8;
+                    diff.pixels[pixel] = Color::RGB(grey, grey, grey);
+                }
+            }
+        }
+    });
+    (changed, diff)
+}
+
+// Renders every snapshot and checks it against its golden image (or rewrites it if bless is set)
+// Returns false if any snapshot failed
+pub fn run_snapshots(ttf: &Sdl2TtfContext, bless: bool) -> bool {
+    let font = ttf
+        .load_font_from_rwops(RWops::from_bytes(FONT).unwrap(), FONT_SIZE)
+        .unwrap();
+    let golden_dir = Path::new(GOLDEN_DIR);
+    let output_dir = Path::new(OUTPUT_DIR);
+    let mut passed = true;
+
+    for snapshot in SNAPSHOTS.iter() {
+        let frame = render_snapshot(snapshot, &font);
+        let golden_path = golden_dir.join(format!("{}.png", snapshot.name));
+
+        if bless {
+            fs::create_dir_all(golden_dir).unwrap();
+            frame.save_png(&golden_path).unwrap();
+            println!("{}: written", snapshot.name);
+            continue;
+        }
+
+        let golden = match Surface::from_file(&golden_path) {
+            Ok(g) => g,
+            Err(_) => {
+                println!(
+                    "{}: skipped, no {} (bless with BLESS=1 to add it)",
+                    snapshot.name,
+                    golden_path.display()
+                );
+                continue;
+            }
+        };
+        if golden.width() != frame.width || golden.height() != frame.height {
+            println!(
+                "{}: golden is {}x{}, frame is {}x{}",
+                snapshot.name,
+                golden.width(),
+                golden.height(),
+                frame.width,
+                frame.height
+            );
+            passed = false;
+            continue;
+        }
+
+        let (changed, diff) = compare(&frame, &golden);
+        let allowed = (frame.width * frame.height) as f64 * MAX_CHANGED;
+        if changed as f64 <= allowed {
+            println!("{}: ok ({} pixels changed)", snapshot.name, changed);
+            continue;
+        }
+
+        fs::create_dir_all(output_dir).unwrap();
+        let actual_path = output_dir.join(format!("{}.actual.png", snapshot.name));
+        let diff_path = output_dir.join(format!("{}.diff.png", snapshot.name));
+        frame.save_png(&actual_path).unwrap();
+        diff.save_png(&diff_path).unwrap();
+        println!(
+            "{}: FAILED, {} pixels changed (see {} and {})",
+            snapshot.name,
+            changed,
+            actual_path.display(),
+            diff_path.display()
+        );
+        passed = false;
+    }
+    passed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshots() {
+        let ttf_context = sdl2::ttf::init().unwrap();
+        let bless = std::env::var_os("BLESS").is_some();
+        assert!(
+            run_snapshots(&ttf_context, bless),
+            "rendering changed, see the output above"
+        );
+    }
+}
@@ -0,0 +1,74 @@
+use sdl2::{pixels::Color, ttf::Font};
+use std::collections::HashMap;
+
+pub const TEXT_CACHE_SIZE: usize = 256; //Rendered strings kept around before the oldest are dropped
+
+// What a rendered string depends on. Fonts have no id, so the font's address stands in for it
+// (together with its size in case a font is freed and another one loaded at the same place)
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct TextKey {
+    font: usize,
+    size: i32,
+    color: (u8, u8, u8, u8),
+    text: String,
+}
+
+impl TextKey {
+    pub fn new(font: &Font, color: Color, text: &str) -> Self {
+        Self {
+            font: font as *const Font as usize,
+            size: font.height(),
+            color: color.rgba(),
+            text: text.to_string(),
+        }
+    }
+}
+
+// Keeps rendered text (textures or surfaces) so unchanged strings are not rendered again every
+// frame, dropping the least recently used entry once it is full
+pub struct TextCache<T> {
+    entries: HashMap<TextKey, (T, u64)>, //Rendered text and the tick it was last used
+    capacity: usize,
+    tick: u64,
+}
+
+impl<T> TextCache<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            capacity: capacity.max(1),
+            tick: 0,
+        }
+    }
+
+    // Gets the cached text, rendering it with render on a miss (None if rendering failed)
+    pub fn get_or_render(
+        &mut self,
+        key: TextKey,
+        render: impl FnOnce() -> Option<T>,
+    ) -> Option<&T> {
+        self.tick += 1;
+        if !self.entries.contains_key(&key) {
+            let value = render()?;
+            if self.entries.len() >= self.capacity {
+                self.evict();
+            }
+            self.entries.insert(key.clone(), (value, self.tick));
+        }
+        let entry = self.entries.get_mut(&key).unwrap();
+        entry.1 = self.tick;
+        Some(&entry.0)
+    }
+
+    // Drops the least recently used entry
+    fn evict(&mut self) {
+        let oldest = self
+            .entries
+            .iter()
+            .min_by_key(|(_, (_, used))| *used)
+            .map(|(key, _)| key.clone());
+        if let Some(key) = oldest {
+            self.entries.remove(&key);
+        }
+    }
+}
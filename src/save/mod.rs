@@ -0,0 +1 @@
+pub mod save;
\ No newline at end of file
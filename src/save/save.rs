@@ -0,0 +1,240 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fs, io, path::Path};
+
+use crate::item::item::ItemStack;
+use crate::player::player::Player;
+use crate::world::world::{chunk_of, World, CHUNK_SIZE};
+
+// Layout of a save directory:
+//   world.toml           time of day, terrain seed and the player (with the tiles it explored)
+//   chunks/<x>_<y>.toml  tiles and dropped items of one chunk
+
+#[derive(Serialize, Deserialize)]
+struct SlotSave {
+    slot: usize,
+    stack: ItemStack,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PlayerSave {
+    pos: (f64, f64),
+    health: i32,
+    inventory: Vec<SlotSave>, //Only the slots that hold something
+    #[serde(default)]
+    explored: Vec<ExploredSave>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ExploredSave {
+    chunk: (i32, i32),
+    rows: Vec<u32>, //One bit per explored tile
+}
+
+#[derive(Serialize, Deserialize)]
+struct WorldSave {
+    time: u32,
+    #[serde(default = "default_seed")]
+    seed: u32,
+    player: PlayerSave,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ItemSave {
+    pos: (f64, f64),
+    age: u32,
+    stack: ItemStack,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ChunkSave {
+    tiles: Vec<i32>, //Row by row, -1 for tiles that were never generated
+    items: Vec<ItemSave>,
+}
+
+// Worlds saved before the seed was stored were all generated with seed 1
+fn default_seed() -> u32 {
+    1
+}
+
+fn to_io<E: std::fmt::Display>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+// Writes the world to a save directory, overwriting chunks that were saved before
+pub fn save_world(world: &World, dir: &Path, tile_size: i32) -> io::Result<()> {
+    fs::create_dir_all(dir.join("chunks"))?;
+
+    let player = &world.player;
+    let mut inventory = Vec::new();
+    for (i, slot) in player.inventory.slots.iter().enumerate() {
+        if let Some(stack) = slot {
+            inventory.push(SlotSave {
+                slot: i,
+                stack: stack.clone(),
+            });
+        }
+    }
+    let meta = WorldSave {
+        time: world.time,
+        seed: world.seed,
+        player: PlayerSave {
+            pos: player.pos,
+            health: player.health.hp,
+            inventory: inventory,
+            explored: player
+                .explored
+                .chunks
+                .iter()
+                .map(|(c, rows)| ExploredSave {
+                    chunk: *c,
+                    rows: rows.clone(),
+                })
+                .collect(),
+        },
+    };
+    fs::write(
+        dir.join("world.toml"),
+        toml::to_string(&meta).map_err(to_io)?,
+    )?;
+
+    // group tiles and items by the chunk they are in
+    let size = CHUNK_SIZE as usize;
+    let mut chunks: HashMap<(i32, i32), ChunkSave> = HashMap::new();
+    for (pos, id) in world.world.iter() {
+        let c = chunk_of(*pos);
+        let chunk = chunks.entry(c).or_insert_with(|| ChunkSave {
+            tiles: vec![-1; size * size],
+            items: Vec::new(),
+        });
+        let local = (pos.0 - c.0 * CHUNK_SIZE, pos.1 - c.1 * CHUNK_SIZE);
+        chunk.tiles[local.1 as usize * size + local.0 as usize] = *id;
+    }
+    for (id, dropped) in world.entities.items.iter() {
+        let pos = *world.entities.positions.get(id).unwrap();
+        let tile = (
+            (pos.0 / tile_size as f64).floor() as i32,
+            (pos.1 / tile_size as f64).floor() as i32,
+        );
+        let chunk = chunks.entry(chunk_of(tile)).or_insert_with(|| ChunkSave {
+            tiles: vec![-1; size * size],
+            items: Vec::new(),
+        });
+        chunk.items.push(ItemSave {
+            pos: pos,
+            age: dropped.age,
+            stack: dropped.stack.clone(),
+        });
+    }
+
+    for (c, chunk) in chunks.iter() {
+        let path = dir.join("chunks").join(format!("{}_{}.toml", c.0, c.1));
+        fs::write(path, toml::to_string(chunk).map_err(to_io)?)?;
+    }
+    Ok(())
+}
+
+// Loads the world saved in dir, or generates a new one if nothing was saved there yet (with
+// the seed if there is one, a random one otherwise)
+pub fn load_or_generate(dir: &Path, seed: Option<u32>, gen_radius: i32) -> io::Result<World> {
+    let mut world = World::new(Player::new());
+    if !load_world(&mut world, dir)? {
+        world.set_seed(seed.unwrap_or_else(rand::random));
+        //initial world generation around the player
+        world.gen((-gen_radius, gen_radius), (-gen_radius, gen_radius));
+    }
+    Ok(world)
+}
+
+// Reads a save directory into the world, returns false if there is no save there
+pub fn load_world(world: &mut World, dir: &Path) -> io::Result<bool> {
+    let meta_path = dir.join("world.toml");
+    if !meta_path.exists() {
+        return Ok(false);
+    }
+    let meta: WorldSave = toml::from_str(&fs::read_to_string(meta_path)?).map_err(to_io)?;
+    world.time = meta.time;
+    world.set_seed(meta.seed);
+    world.player.pos = meta.player.pos;
+    world.player.health.hp = meta.player.health;
+    for slot in meta.player.inventory {
+        if slot.slot < world.player.inventory.slots.len() {
+            world.player.inventory.slots[slot.slot] = Some(slot.stack);
+        }
+    }
+    for e in meta.player.explored {
+        if e.rows.len() == CHUNK_SIZE as usize {
+            world.player.explored.chunks.insert(e.chunk, e.rows);
+        }
+    }
+
+    let size = CHUNK_SIZE as usize;
+    for entry in fs::read_dir(dir.join("chunks"))? {
+        let path = entry?.path();
+        let name = match path.file_stem().and_then(|s| s.to_str()) {
+            Some(n) => n.to_string(),
+            None => continue,
+        };
+        let c: Vec<i32> = name.split('_').filter_map(|n| n.parse().ok()).collect();
+        if c.len() != 2 {
+            continue;
+        }
+        let chunk: ChunkSave = toml::from_str(&fs::read_to_string(&path)?).map_err(to_io)?;
+        for (i, id) in chunk.tiles.iter().enumerate() {
+            if *id < 0 {
+                continue;
+            }
+            // an outdated or broken save would crash later on a tile nothing knows how to draw
+            if !world.tiles.contains_key(id) {
+                return Err(to_io(format!("Unknown tile {} in {}", id, path.display())));
+            }
+            let pos = (
+                c[0] * CHUNK_SIZE + (i % size) as i32,
+                c[1] * CHUNK_SIZE + (i / size) as i32,
+            );
+            world.world.insert(pos, *id);
+        }
+        world.changed_chunks.insert((c[0], c[1]));
+        for item in chunk.items {
+            world.spawn_item(item.pos, item.stack, item.age);
+        }
+    }
+    world.revision += 1;
+    world.edits += 1;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Empty directory under target for a test to save into
+    fn save_dir(name: &str) -> std::path::PathBuf {
+        let dir = Path::new("target/test_saves").join(name);
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn tiles_survive_a_save_and_load() {
+        let dir = save_dir("round_trip");
+        let mut world = World::new(Player::new());
+        world.set_tile((3, -4), 6);
+        world.set_tile((40, 2), 2);
+        save_world(&world, &dir, 50).unwrap();
+
+        let loaded = load_or_generate(&dir, None, 0).unwrap();
+        assert_eq!(loaded.world, world.world);
+    }
+
+    #[test]
+    fn unknown_tile_ids_are_an_error() {
+        let dir = save_dir("unknown_tile");
+        let mut world = World::new(Player::new());
+        world.set_tile((0, 0), 9999);
+        save_world(&world, &dir, 50).unwrap();
+
+        let e = load_or_generate(&dir, None, 0).err().unwrap();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        assert!(e.to_string().contains("Unknown tile 9999"));
+    }
+}
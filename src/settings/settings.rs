@@ -0,0 +1,315 @@
+use serde::{Deserialize, Serialize};
+use std::{fs, io, path::Path};
+
+pub const SETTINGS_PATH: &str = "config/settings.toml";
+pub const MAX_FPS: u32 = 240; //Highest frame rate cap, 0 is no cap at all
+
+//Everything the player can configure, loaded from the settings file and overridden from the
+//command line. Fields missing from the file keep their defaults. The game keeps the file's own
+//values apart from the ones in use, so options and F11 aren't saved into the file
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub width: u32, //Window size
+    pub height: u32,
+    pub fullscreen: bool,
+    pub resizable: bool,
+    pub high_dpi: bool, //Draws at the screen's full resolution, scaled so the game keeps its size
+    pub logical_width: u32, //Fixed resolution scaled to fit the window with black bars around it,
+    pub logical_height: u32, //0 draws at whatever size the window is
+    pub vsync: bool,
+    pub fps: u32, //Frame rate cap, 0 for none (the world always ticks 30 times a second)
+    pub ui_scale: f64, //Multiplier on the font size
+    pub font: String,
+    pub font_size: u16,
+    pub tile_size: i32,  //Size of a tile in world units
+    pub gen_radius: i32, //Tiles generated around the spawn of a new world
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u32>, //Terrain seed of new worlds, random when not set
+    pub save_dir: String, //Every world is saved in a directory in here
+    pub master_volume: f64, //Volumes are 0 - 1
+    pub music_volume: f64,
+    pub effects_volume: f64,
+    pub ui_volume: f64, //Menu clicks
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            width: 800,
+            height: 600,
+            fullscreen: false,
+            resizable: true,
+            high_dpi: true,
+            logical_width: 0,
+            logical_height: 0,
+            vsync: true,
+            fps: 60,
+            ui_scale: 1.0,
+            font: "assets/fonts/vcr_osd_mono.ttf".to_string(),
+            font_size: 32,
+            tile_size: 50,
+            gen_radius: 100,
+            seed: None,
+            save_dir: "saves".to_string(),
+            master_volume: 1.0,
+            music_volume: 0.6,
+            effects_volume: 0.8,
+            ui_volume: 0.8,
+        }
+    }
+}
+
+// Gets the value after an option
+fn value<T: std::str::FromStr>(args: &[String], i: usize) -> Result<T, String> {
+    let v = args
+        .get(i + 1)
+        .ok_or_else(|| format!("{} needs a value", args[i]))?;
+    v.parse()
+        .map_err(|_| format!("Bad value {} for {}", v, args[i]))
+}
+
+impl Settings {
+    // Loads the settings file, a missing file gives the defaults and values out of range are
+    // clamped
+    pub fn load(path: &Path) -> Self {
+        let text = match fs::read_to_string(path) {
+            Ok(t) => t,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Self::default(),
+            Err(e) => {
+                println!("Could not read {}: {}", path.display(), e);
+                return Self::default();
+            }
+        };
+        match toml::from_str::<Self>(&text) {
+            Ok(mut s) => {
+                s.clamp();
+                s
+            }
+            Err(e) => {
+                println!("Could not parse {}: {}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let text = toml::to_string(self).map_err(io::Error::other)?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, text)
+    }
+
+    // Overrides settings from command line options. Options main reads itself (like --terminal)
+    // are skipped, any other unknown option is an error
+    pub fn apply_args(&mut self, args: &[String]) -> Result<(), String> {
+        let mut i = 1;
+        while i < args.len() {
+            let mut takes_value = true;
+            match args[i].as_str() {
+                "--width" => self.width = value(args, i)?,
+                "--height" => self.height = value(args, i)?,
+                "--logical-width" => self.logical_width = value(args, i)?,
+                "--logical-height" => self.logical_height = value(args, i)?,
+                "--fps" => self.fps = value(args, i)?,
+                "--ui-scale" => self.ui_scale = value(args, i)?,
+                "--seed" => self.seed = Some(value(args, i)?),
+                "--save-dir" => self.save_dir = value(args, i)?,
+                "--volume" => self.master_volume = value(args, i)?,
+                "--music-volume" => self.music_volume = value(args, i)?,
+                "--effects-volume" => self.effects_volume = value(args, i)?,
+                "--ui-volume" => self.ui_volume = value(args, i)?,
+                "--script" => {
+                    value::<String>(args, i)?;
+                }
+                _ => {
+                    takes_value = false;
+                    match args[i].as_str() {
+                        "--fullscreen" => self.fullscreen = true,
+                        "--windowed" => self.fullscreen = false,
+                        "--resizable" => self.resizable = true,
+                        "--fixed-size" => self.resizable = false,
+                        "--high-dpi" => self.high_dpi = true,
+                        "--no-high-dpi" => self.high_dpi = false,
+                        "--vsync" => self.vsync = true,
+                        "--no-vsync" => self.vsync = false,
+                        "--snapshot" | "--bless" | "--terminal" => {}
+                        other => return Err(format!("Unknown option {}", other)),
+                    }
+                }
+            }
+            i += if takes_value { 2 } else { 1 };
+        }
+        self.clamp();
+        Ok(())
+    }
+
+    // Keeps values in ranges the game can run with
+    pub fn clamp(&mut self) {
+        self.width = self.width.max(320);
+        self.height = self.height.max(240);
+        self.fps = self.fps.min(MAX_FPS);
+        self.font_size = self.font_size.clamp(8, 128);
+        self.ui_scale = self.ui_scale.clamp(0.5, 3.0);
+        self.tile_size = self.tile_size.max(1);
+        self.gen_radius = self.gen_radius.max(0);
+        self.master_volume = self.master_volume.clamp(0.0, 1.0);
+        self.music_volume = self.music_volume.clamp(0.0, 1.0);
+        self.effects_volume = self.effects_volume.clamp(0.0, 1.0);
+        self.ui_volume = self.ui_volume.clamp(0.0, 1.0);
+    }
+
+    // Makes a change from the menus to these settings (the ones in use) and to saved, the
+    // settings file's own values
+    pub fn change(&mut self, saved: &mut Settings, apply: impl Fn(&mut Settings)) {
+        apply(self);
+        apply(saved);
+    }
+
+    // Fixed resolution the game is drawn at, if one is set
+    pub fn logical_size(&self) -> Option<(u32, u32)> {
+        if self.logical_width > 0 && self.logical_height > 0 {
+            Some((self.logical_width, self.logical_height))
+        } else {
+            None
+        }
+    }
+
+    // Font size with the ui scale applied
+    pub fn scaled_font_size(&self) -> u16 {
+        (self.font_size as f64 * self.ui_scale).round().max(1.0) as u16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    // Settings file under target for a test, removed first so it starts missing
+    fn test_path(name: &str) -> PathBuf {
+        let path = Path::new("target/test_settings").join(format!("{}.toml", name));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    // Command line with the program name in front, like std::env::args
+    fn args(options: &[&str]) -> Vec<String> {
+        std::iter::once("game")
+            .chain(options.iter().copied())
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let path = test_path("round_trip");
+        let settings = Settings {
+            width: 1024,
+            fullscreen: true,
+            seed: Some(42),
+            music_volume: 0.25,
+            ..Settings::default()
+        };
+        settings.save(&path).unwrap();
+
+        let loaded = Settings::load(&path);
+        assert_eq!(
+            toml::to_string(&loaded).unwrap(),
+            toml::to_string(&settings).unwrap()
+        );
+    }
+
+    #[test]
+    fn missing_file_and_fields_keep_the_defaults() {
+        let path = test_path("partial");
+        assert_eq!(Settings::load(&path).width, Settings::default().width);
+
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "width = 1000\n").unwrap();
+        let loaded = Settings::load(&path);
+        assert_eq!(loaded.width, 1000);
+        assert_eq!(loaded.height, Settings::default().height);
+    }
+
+    #[test]
+    fn options_override_the_file() {
+        let mut settings = Settings::default();
+        let options = args(&[
+            "--width",
+            "1280",
+            "--fullscreen",
+            "--no-vsync",
+            "--seed",
+            "7",
+            "--script",
+            "start.txt",
+            "--terminal",
+        ]);
+        settings.apply_args(&options).unwrap();
+        assert_eq!(settings.width, 1280);
+        assert!(settings.fullscreen);
+        assert!(!settings.vsync);
+        assert_eq!(settings.seed, Some(7));
+    }
+
+    #[test]
+    fn bad_options_are_errors() {
+        let mut settings = Settings::default();
+        assert!(settings.apply_args(&args(&["--widht", "1280"])).is_err());
+        assert!(settings.apply_args(&args(&["--width", "wide"])).is_err());
+        assert!(settings.apply_args(&args(&["--width"])).is_err());
+        assert!(settings.apply_args(&args(&["--script"])).is_err());
+        assert!(settings.apply_args(&args(&["world"])).is_err());
+    }
+
+    #[test]
+    fn values_out_of_range_are_clamped() {
+        let path = test_path("out_of_range");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let text = "width = 10\nheight = 10\nfps = 100000\ntile_size = 0\nfont_size = 0\n\
+                    master_volume = 3.0\nmusic_volume = -1.0\nui_scale = 10.0\n";
+        fs::write(&path, text).unwrap();
+        let loaded = Settings::load(&path);
+        assert_eq!((loaded.width, loaded.height), (320, 240));
+        assert_eq!(loaded.fps, MAX_FPS);
+        assert_eq!(loaded.tile_size, 1);
+        assert_eq!(loaded.font_size, 8);
+        assert_eq!(loaded.master_volume, 1.0);
+        assert_eq!(loaded.music_volume, 0.0);
+        assert_eq!(loaded.ui_scale, 3.0);
+
+        let mut settings = Settings::default();
+        settings.apply_args(&args(&["--volume", "2"])).unwrap();
+        assert_eq!(settings.master_volume, 1.0);
+    }
+
+    #[test]
+    fn only_file_values_and_menu_changes_are_saved() {
+        let path = test_path("split");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "width = 1000\nmusic_volume = 0.5\n").unwrap();
+
+        // what main does: the file's values, then the options on a copy of them
+        let mut saved = Settings::load(&path);
+        let mut settings = saved.clone();
+        settings
+            .apply_args(&args(&["--width", "1280", "--fps", "30"]))
+            .unwrap();
+        // F11
+        settings.fullscreen = !settings.fullscreen;
+        // a volume slider in the audio menu
+        settings.change(&mut saved, |s| s.music_volume = 0.2);
+        saved.save(&path).unwrap();
+
+        assert_eq!(settings.width, 1280);
+        assert!(settings.fullscreen);
+        assert_eq!(settings.music_volume, 0.2);
+        let file = Settings::load(&path);
+        assert_eq!(file.width, 1000);
+        assert_eq!(file.fps, Settings::default().fps);
+        assert!(!file.fullscreen);
+        assert_eq!(file.music_volume, 0.2);
+    }
+}
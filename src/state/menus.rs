@@ -0,0 +1,840 @@
+use sdl2::{
+    controller::Button, event::Event, keyboard::Keycode, pixels::Color, rect::Rect, ttf::Font,
+};
+use std::{fs, path::Path};
+
+use crate::audio::audio::{Audio, Category, UI_CLICK};
+use crate::gui::gui::{Gui, GuiEvent};
+use crate::gui::widget::{Anchor, Layout, Widget, WidgetKind, ROW_HEIGHT};
+use crate::input::input::{pressed_input, Action, Bindings, Input, ACTIONS, BINDINGS_PATH};
+use crate::item::item::HOTBAR_SIZE;
+use crate::player::player::Player;
+use crate::renderer::{
+    backend::RenderBackend,
+    camera::{MAX_ZOOM, MIN_ZOOM},
+    renderer::{RenderMode, Renderer},
+};
+use crate::settings::settings::{Settings, MAX_FPS, SETTINGS_PATH};
+use crate::state::playing::Playing;
+use crate::state::state::{Game, State, Transition};
+use crate::world::world::World;
+
+const BACKGROUND: Color = Color::RGB(15, 15, 25); //Behind full screen menus
+const DIM: Color = Color::RGBA(0, 0, 0, 150); //Over the frozen state below an overlay
+const BUTTON_SIZE: (u32, u32) = (300, 40);
+
+// Centered column with a title and a button per (id, text)
+fn menu(title: &str, buttons: &[(&str, &str)]) -> Widget {
+    let mut panel = Widget::panel("menu", Layout::Vertical).at(Anchor::Center, (0, 0));
+    panel.padding = 16;
+    panel.spacing = 10;
+    panel.children.push(Widget::label("title", title));
+    for (id, text) in buttons.iter() {
+        panel
+            .children
+            .push(Widget::button(id, text).sized(BUTTON_SIZE.0, BUTTON_SIZE.1));
+    }
+    panel
+}
+
+// Takes the events of a menu's gui, clicking a button or a list entry makes the click sound
+fn gui_events(gui: &mut Gui, audio: &mut Audio) -> Vec<GuiEvent> {
+    let events: Vec<GuiEvent> = gui.events.drain(..).collect();
+    let clicked = events
+        .iter()
+        .any(|e| matches!(e, GuiEvent::Clicked(_) | GuiEvent::Selected(..)));
+    if clicked {
+        audio.play(UI_CLICK, Category::Ui);
+    }
+    events
+}
+
+fn gui_with(widget: Widget) -> Gui {
+    let mut gui = Gui::new();
+    gui.add(widget);
+    gui
+}
+
+fn draw_menu(gui: &mut Gui, canvas: &mut dyn RenderBackend, font: &Font, overlay: bool) {
+    let (w, h) = canvas.size();
+    canvas.fill_rect(
+        Rect::new(0, 0, w, h),
+        if overlay { DIM } else { BACKGROUND },
+    );
+    gui.draw(canvas, font, None);
+}
+
+// Escape, or B or start on a controller, backs out of a menu
+fn escape(event: &Event) -> bool {
+    matches!(
+        event,
+        Event::KeyDown {
+            keycode: Some(Keycode::Escape),
+            repeat: false,
+            ..
+        } | Event::ControllerButtonDown {
+            button: Button::B | Button::Start,
+            ..
+        }
+    )
+}
+
+// Directory names of the worlds saved in dir, sorted
+fn saved_worlds(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = match fs::read_dir(dir) {
+        Ok(dir) => dir
+            .flatten()
+            .filter(|e| e.path().join("world.toml").exists())
+            .filter_map(|e| e.file_name().into_string().ok())
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    names.sort();
+    names
+}
+
+// Keeps the characters of a world name that are safe in a directory name
+fn world_dir_name(name: &str) -> String {
+    name.trim()
+        .chars()
+        .filter(|c| c.is_alphanumeric() || *c == ' ' || *c == '-' || *c == '_')
+        .collect()
+}
+
+//First screen, before any world is open
+pub struct MainMenu {
+    gui: Gui,
+}
+
+impl MainMenu {
+    pub fn new() -> Self {
+        Self {
+            gui: gui_with(menu(
+                "Rust Game",
+                &[("play", "Play"), ("settings", "Settings"), ("quit", "Quit")],
+            )),
+        }
+    }
+}
+
+impl State for MainMenu {
+    fn handle_event(&mut self, game: &mut Game, event: &Event) -> Transition {
+        self.gui.handle_event(event);
+        for e in gui_events(&mut self.gui, &mut game.audio) {
+            if let GuiEvent::Clicked(id) = e {
+                match id.as_str() {
+                    "play" => {
+                        let dir = Path::new(&game.settings.save_dir);
+                        return Transition::Push(Box::new(WorldSelect::new(dir)));
+                    }
+                    "settings" => {
+                        return Transition::Push(Box::new(SettingsMenu::new(&game.render)))
+                    }
+                    "quit" => return Transition::Quit,
+                    _ => {}
+                }
+            }
+        }
+        Transition::None
+    }
+
+    fn render(&mut self, _game: &mut Game, canvas: &mut dyn RenderBackend, font: &Font) {
+        draw_menu(&mut self.gui, canvas, font, false);
+    }
+}
+
+//List of saved worlds to load, and a name field to create a new one
+pub struct WorldSelect {
+    gui: Gui,
+    worlds: Vec<String>,
+    selected: Option<usize>,
+}
+
+impl WorldSelect {
+    pub fn new(dir: &Path) -> Self {
+        let worlds = saved_worlds(dir);
+        let mut panel = menu("Worlds", &[]);
+        panel.children.push(
+            Widget::scroll_list("worlds", worlds.clone())
+                .sized(BUTTON_SIZE.0, ROW_HEIGHT as u32 * 6),
+        );
+        panel
+            .children
+            .push(Widget::text_input("name", "New world name").sized(BUTTON_SIZE.0, 0));
+        let mut row = Widget::panel("buttons", Layout::Horizontal);
+        row.background = None;
+        row.padding = 0;
+        for (id, text) in [("load", "Load"), ("create", "Create"), ("back", "Back")] {
+            row.children
+                .push(Widget::button(id, text).sized(96, BUTTON_SIZE.1));
+        }
+        panel.children.push(row);
+        let mut error = Widget::label("error", "");
+        error.kind = WidgetKind::Label {
+            text: String::new(),
+            color: Color::RGB(230, 70, 70),
+        };
+        panel.children.push(error);
+        Self {
+            gui: gui_with(panel),
+            worlds: worlds,
+            selected: None,
+        }
+    }
+
+    fn open(&mut self, game: &mut Game, name: &str) -> Transition {
+        let dir = Path::new(&game.settings.save_dir).join(name);
+        match game.open_world(&dir) {
+            Ok(()) => Transition::Switch(Box::new(Playing)),
+            Err(e) => {
+                self.gui
+                    .set_text("error", &format!("Could not open {}: {}", name, e));
+                Transition::None
+            }
+        }
+    }
+
+    fn create(&mut self, game: &mut Game, name: &str) -> Transition {
+        let name = world_dir_name(name);
+        if name.is_empty() {
+            self.gui.set_text("error", "Type a name for the new world");
+            return Transition::None;
+        }
+        if self.worlds.contains(&name) {
+            self.gui
+                .set_text("error", "A world with that name already exists");
+            return Transition::None;
+        }
+        self.open(game, &name)
+    }
+}
+
+impl State for WorldSelect {
+    fn handle_event(&mut self, game: &mut Game, event: &Event) -> Transition {
+        if !self.gui.handle_event(event) && escape(event) {
+            return Transition::Pop;
+        }
+        let events = gui_events(&mut self.gui, &mut game.audio);
+        for e in events {
+            let transition = match e {
+                GuiEvent::Selected(_, i) => {
+                    self.selected = Some(i);
+                    Transition::None
+                }
+                GuiEvent::Submitted(_, name) => self.create(game, &name),
+                GuiEvent::Clicked(id) => match id.as_str() {
+                    "load" => match self.selected.and_then(|i| self.worlds.get(i)).cloned() {
+                        Some(name) => self.open(game, &name),
+                        None => {
+                            self.gui.set_text("error", "Select a world to load");
+                            Transition::None
+                        }
+                    },
+                    "create" => {
+                        let name = match self.gui.get("name").map(|w| &w.kind) {
+                            Some(WidgetKind::TextInput { text, .. }) => text.clone(),
+                            _ => String::new(),
+                        };
+                        self.create(game, &name)
+                    }
+                    "back" => Transition::Pop,
+                    _ => Transition::None,
+                },
+                _ => Transition::None,
+            };
+            if !matches!(transition, Transition::None) {
+                return transition;
+            }
+        }
+        Transition::None
+    }
+
+    fn render(&mut self, _game: &mut Game, canvas: &mut dyn RenderBackend, font: &Font) {
+        draw_menu(&mut self.gui, canvas, font, false);
+    }
+}
+
+//Escape menu over the frozen world
+pub struct Paused {
+    gui: Gui,
+}
+
+impl Paused {
+    pub fn new() -> Self {
+        Self {
+            gui: gui_with(menu(
+                "Paused",
+                &[
+                    ("resume", "Resume"),
+                    ("settings", "Settings"),
+                    ("quit_menu", "Quit to menu"),
+                ],
+            )),
+        }
+    }
+}
+
+impl State for Paused {
+    fn handle_event(&mut self, game: &mut Game, event: &Event) -> Transition {
+        if !self.gui.handle_event(event) && escape(event) {
+            return Transition::Pop;
+        }
+        for e in gui_events(&mut self.gui, &mut game.audio) {
+            if let GuiEvent::Clicked(id) = e {
+                match id.as_str() {
+                    "resume" => return Transition::Pop,
+                    "settings" => {
+                        return Transition::Push(Box::new(SettingsMenu::new(&game.render)))
+                    }
+                    "quit_menu" => {
+                        game.close_world();
+                        return Transition::Switch(Box::new(MainMenu::new()));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Transition::None
+    }
+
+    fn render(&mut self, _game: &mut Game, canvas: &mut dyn RenderBackend, font: &Font) {
+        draw_menu(&mut self.gui, canvas, font, true);
+    }
+
+    fn overlay(&self) -> bool {
+        true
+    }
+}
+
+fn mode_text(mode: RenderMode) -> String {
+    format!(
+        "Render mode: {}",
+        match mode {
+            RenderMode::Sprites => "Sprites",
+            RenderMode::Glyphs => "Glyphs",
+        }
+    )
+}
+
+//Render mode and zoom, and the video, audio and controls menus, reachable from the main menu and the pause menu
+pub struct SettingsMenu {
+    gui: Gui,
+}
+
+impl SettingsMenu {
+    pub fn new(render: &Renderer) -> Self {
+        let mut panel = menu("Settings", &[("render_mode", &mode_text(render.mode))]);
+        panel.children.push(Widget::label("zoom_label", "Zoom"));
+        panel.children.push(
+            Widget::slider("zoom", render.camera.zoom, MIN_ZOOM, MAX_ZOOM).sized(BUTTON_SIZE.0, 24),
+        );
+        for (id, text) in [
+            ("video", "Video"),
+            ("audio", "Audio"),
+            ("controls", "Controls"),
+            ("back", "Back"),
+        ] {
+            panel
+                .children
+                .push(Widget::button(id, text).sized(BUTTON_SIZE.0, BUTTON_SIZE.1));
+        }
+        Self {
+            gui: gui_with(panel),
+        }
+    }
+}
+
+impl State for SettingsMenu {
+    fn handle_event(&mut self, game: &mut Game, event: &Event) -> Transition {
+        if !self.gui.handle_event(event) && escape(event) {
+            return Transition::Pop;
+        }
+        let events = gui_events(&mut self.gui, &mut game.audio);
+        for e in events {
+            match e {
+                GuiEvent::Clicked(id) if id == "render_mode" => {
+                    let render = &mut game.render;
+                    render.mode = match render.mode {
+                        RenderMode::Sprites => RenderMode::Glyphs,
+                        RenderMode::Glyphs => RenderMode::Sprites,
+                    };
+                    self.gui.set_text("render_mode", &mode_text(render.mode));
+                }
+                GuiEvent::Clicked(id) if id == "video" => {
+                    return Transition::Push(Box::new(VideoSettings::new(game)))
+                }
+                GuiEvent::Clicked(id) if id == "audio" => {
+                    return Transition::Push(Box::new(AudioSettings::new(&mut game.settings)))
+                }
+                GuiEvent::Clicked(id) if id == "controls" => {
+                    return Transition::Push(Box::new(Controls::new(&game.input.bindings)))
+                }
+                GuiEvent::Clicked(id) if id == "back" => return Transition::Pop,
+                GuiEvent::Changed(_, zoom) => game.render.camera.zoom = zoom,
+                _ => {}
+            }
+        }
+        Transition::None
+    }
+
+    fn render(&mut self, _game: &mut Game, canvas: &mut dyn RenderBackend, font: &Font) {
+        draw_menu(&mut self.gui, canvas, font, true);
+    }
+
+    fn overlay(&self) -> bool {
+        true
+    }
+}
+
+fn on_off(on: bool) -> &'static str {
+    if on {
+        "On"
+    } else {
+        "Off"
+    }
+}
+
+// Label showing a value with a slider under it, the label's id is the slider's with _label
+fn slider_row(panel: &mut Widget, id: &str, text: &str, value: f64, range: (f64, f64)) {
+    panel
+        .children
+        .push(Widget::label(&format!("{}_label", id), text));
+    panel
+        .children
+        .push(Widget::slider(id, value, range.0, range.1).sized(BUTTON_SIZE.0, 24));
+}
+
+// Makes a menu change to the settings in use and to the ones saved in the settings file
+fn change(game: &mut Game, apply: impl Fn(&mut Settings)) {
+    game.settings.change(&mut game.saved_settings, apply);
+}
+
+// Writes the settings file so changes are kept for the next start, command line options and
+// F11 aren't in it
+fn save_settings(game: &Game) {
+    if let Err(e) = game.saved_settings.save(Path::new(SETTINGS_PATH)) {
+        println!("Could not save settings: {}", e);
+    }
+}
+
+fn fps_text(fps: u32) -> String {
+    if fps == 0 {
+        "FPS cap: none".to_string()
+    } else {
+        format!("FPS cap: {}", fps)
+    }
+}
+
+//Window and frame rate settings, vsync and the ui scale apply after a restart
+pub struct VideoSettings {
+    gui: Gui,
+}
+
+impl VideoSettings {
+    pub fn new(game: &Game) -> Self {
+        let s = &game.settings;
+        let mut panel = menu(
+            "Video",
+            &[
+                (
+                    "fullscreen",
+                    &format!("Fullscreen: {}", on_off(s.fullscreen)),
+                ),
+                ("vsync", &format!("VSync: {}", on_off(s.vsync))),
+            ],
+        );
+        slider_row(
+            &mut panel,
+            "fps",
+            &fps_text(s.fps),
+            s.fps as f64,
+            (0.0, MAX_FPS as f64),
+        );
+        slider_row(
+            &mut panel,
+            "ui_scale",
+            &format!("UI scale: {:.2}", s.ui_scale),
+            s.ui_scale,
+            (0.5, 3.0),
+        );
+        panel.children.push(Widget::label(
+            "hint",
+            "VSync and UI scale apply on restart",
+        ));
+        panel
+            .children
+            .push(Widget::button("back", "Back").sized(BUTTON_SIZE.0, BUTTON_SIZE.1));
+        Self {
+            gui: gui_with(panel),
+        }
+    }
+}
+
+impl State for VideoSettings {
+    fn handle_event(&mut self, game: &mut Game, event: &Event) -> Transition {
+        if !self.gui.handle_event(event) && escape(event) {
+            save_settings(game);
+            return Transition::Pop;
+        }
+        let events = gui_events(&mut self.gui, &mut game.audio);
+        for e in events {
+            match e {
+                GuiEvent::Clicked(id) if id == "fullscreen" => {
+                    let on = !game.settings.fullscreen;
+                    change(game, |s| s.fullscreen = on);
+                    self.gui
+                        .set_text(&id, &format!("Fullscreen: {}", on_off(on)));
+                }
+                GuiEvent::Clicked(id) if id == "vsync" => {
+                    let on = !game.settings.vsync;
+                    change(game, |s| s.vsync = on);
+                    self.gui
+                        .set_text(&id, &format!("VSync: {}", on_off(on)));
+                }
+                GuiEvent::Clicked(id) if id == "back" => {
+                    save_settings(game);
+                    return Transition::Pop;
+                }
+                // the frame rate cap is read every frame, so it applies right away
+                GuiEvent::Changed(id, v) if id == "fps" => {
+                    let fps = v.round() as u32;
+                    change(game, |s| s.fps = fps);
+                    self.gui.set_text("fps_label", &fps_text(fps));
+                }
+                GuiEvent::Changed(id, v) if id == "ui_scale" => {
+                    let scale = (v * 20.0).round() / 20.0;
+                    change(game, |s| s.ui_scale = scale);
+                    self.gui
+                        .set_text("ui_scale_label", &format!("UI scale: {:.2}", scale));
+                }
+                _ => {}
+            }
+        }
+        Transition::None
+    }
+
+    fn render(&mut self, _game: &mut Game, canvas: &mut dyn RenderBackend, font: &Font) {
+        draw_menu(&mut self.gui, canvas, font, true);
+    }
+
+    fn overlay(&self) -> bool {
+        true
+    }
+}
+
+type Volume = fn(&mut Settings) -> &mut f64;
+
+// Volume sliders: slider id, label and the setting it changes
+const VOLUMES: [(&str, &str, Volume); 4] = [
+    ("master_volume", "Master", |s| &mut s.master_volume),
+    ("music_volume", "Music", |s| &mut s.music_volume),
+    ("effects_volume", "Effects", |s| &mut s.effects_volume),
+    ("ui_volume", "Interface", |s| &mut s.ui_volume),
+];
+
+//Volume of everything, the music, the sound effects and the menu sounds
+pub struct AudioSettings {
+    gui: Gui,
+}
+
+impl AudioSettings {
+    pub fn new(settings: &mut Settings) -> Self {
+        let mut panel = menu("Audio", &[]);
+        for (id, text, volume) in VOLUMES.iter() {
+            let v = *volume(settings);
+            slider_row(
+                &mut panel,
+                id,
+                &format!("{}: {:.0}%", text, v * 100.0),
+                v,
+                (0.0, 1.0),
+            );
+        }
+        panel
+            .children
+            .push(Widget::button("back", "Back").sized(BUTTON_SIZE.0, BUTTON_SIZE.1));
+        Self {
+            gui: gui_with(panel),
+        }
+    }
+}
+
+impl State for AudioSettings {
+    fn handle_event(&mut self, game: &mut Game, event: &Event) -> Transition {
+        if !self.gui.handle_event(event) && escape(event) {
+            save_settings(game);
+            return Transition::Pop;
+        }
+        let events = gui_events(&mut self.gui, &mut game.audio);
+        for e in events {
+            match e {
+                GuiEvent::Clicked(id) if id == "back" => {
+                    save_settings(game);
+                    return Transition::Pop;
+                }
+                GuiEvent::Changed(id, v) => {
+                    if let Some((_, text, volume)) = VOLUMES.iter().find(|(i, _, _)| *i == id) {
+                        change(game, |s| *volume(s) = v);
+                        self.gui.set_text(
+                            &format!("{}_label", id),
+                            &format!("{}: {:.0}%", text, v * 100.0),
+                        );
+                    }
+                }
+                _ => {}
+            }
+        }
+        Transition::None
+    }
+
+    fn render(&mut self, _game: &mut Game, canvas: &mut dyn RenderBackend, font: &Font) {
+        draw_menu(&mut self.gui, canvas, font, true);
+    }
+
+    fn overlay(&self) -> bool {
+        true
+    }
+}
+
+// One line per action with the inputs bound to it
+fn binding_lines(bindings: &Bindings) -> Vec<String> {
+    ACTIONS
+        .iter()
+        .map(|(action, name)| {
+            let inputs: Vec<String> = bindings.get(*action).iter().map(|i| i.name()).collect();
+            format!("{}: {}", name.replace('_', " "), inputs.join(", "))
+        })
+        .collect()
+}
+
+//Rebinding: selecting an action waits for the next key or mouse button and binds it
+pub struct Controls {
+    gui: Gui,
+    waiting: Option<Action>, //Action the next input gets bound to
+}
+
+impl Controls {
+    pub fn new(bindings: &Bindings) -> Self {
+        let mut panel = menu("Controls", &[]);
+        panel.children.push(
+            Widget::scroll_list("bindings", binding_lines(bindings))
+                .sized(BUTTON_SIZE.0 + 160, ROW_HEIGHT as u32 * 8),
+        );
+        panel
+            .children
+            .push(Widget::label("hint", "Select an action to rebind it"));
+        let mut row = Widget::panel("buttons", Layout::Horizontal);
+        row.background = None;
+        row.padding = 0;
+        for (id, text) in [("reset", "Defaults"), ("back", "Back")] {
+            row.children
+                .push(Widget::button(id, text).sized(BUTTON_SIZE.0 / 2, BUTTON_SIZE.1));
+        }
+        panel.children.push(row);
+        Self {
+            gui: gui_with(panel),
+            waiting: None,
+        }
+    }
+
+    fn refresh(&mut self, bindings: &Bindings) {
+        let lines = binding_lines(bindings);
+        if let Some(WidgetKind::ScrollList { items, .. }) =
+            self.gui.get_mut("bindings").map(|w| &mut w.kind)
+        {
+            *items = lines;
+        }
+    }
+
+    // Writes the bindings so they are kept for the next start
+    fn close(&self, game: &Game) -> Transition {
+        if let Err(e) = game.input.bindings.save(Path::new(BINDINGS_PATH)) {
+            println!("Could not save bindings: {}", e);
+        }
+        Transition::Pop
+    }
+}
+
+impl State for Controls {
+    fn handle_event(&mut self, game: &mut Game, event: &Event) -> Transition {
+        if let Some(action) = self.waiting {
+            if let Some(input) = pressed_input(event) {
+                // escape cancels instead of being bound, it has to keep closing menus
+                if input != Input::Key(Keycode::Escape) {
+                    game.input.bindings.bind(action, input);
+                    self.refresh(&game.input.bindings);
+                }
+                self.waiting = None;
+                self.gui.set_text("hint", "Select an action to rebind it");
+            }
+            return Transition::None;
+        }
+        if !self.gui.handle_event(event) && escape(event) {
+            return self.close(game);
+        }
+        let events = gui_events(&mut self.gui, &mut game.audio);
+        for e in events {
+            match e {
+                GuiEvent::Selected(_, i) => {
+                    let (action, name) = ACTIONS[i];
+                    self.waiting = Some(action);
+                    self.gui.set_text(
+                        "hint",
+                        &format!("Press a key or button for {}", name.replace('_', " ")),
+                    );
+                }
+                GuiEvent::Clicked(id) if id == "reset" => {
+                    game.input.bindings = Bindings::new();
+                    self.refresh(&game.input.bindings);
+                }
+                GuiEvent::Clicked(id) if id == "back" => return self.close(game),
+                _ => {}
+            }
+        }
+        Transition::None
+    }
+
+    fn render(&mut self, _game: &mut Game, canvas: &mut dyn RenderBackend, font: &Font) {
+        draw_menu(&mut self.gui, canvas, font, true);
+    }
+
+    fn overlay(&self) -> bool {
+        true
+    }
+}
+
+// One line per inventory slot, hotbar slots are numbered and the selected one marked
+fn slot_lines(world: &World) -> Vec<String> {
+    let inventory = &world.player.inventory;
+    inventory
+        .slots
+        .iter()
+        .enumerate()
+        .map(|(i, slot)| {
+            let prefix = if i == inventory.selected {
+                format!("> {}", i + 1)
+            } else if i < HOTBAR_SIZE {
+                format!("  {}", i + 1)
+            } else {
+                "   ".to_string()
+            };
+            match slot {
+                Some(s) if s.count > 1 => format!("{} {} x{}", prefix, s.item, s.count),
+                Some(s) => format!("{} {}", prefix, s.item),
+                None => format!("{} -", prefix),
+            }
+        })
+        .collect()
+}
+
+//Every inventory slot, selecting a hotbar slot selects it and selecting any other slot
+//swaps it with the selected one
+pub struct InventoryScreen {
+    gui: Gui,
+}
+
+impl InventoryScreen {
+    pub fn new(world: &World) -> Self {
+        let mut panel = menu("Inventory", &[]);
+        panel.children.push(
+            Widget::scroll_list("slots", slot_lines(world))
+                .sized(BUTTON_SIZE.0 + 100, ROW_HEIGHT as u32 * 10),
+        );
+        Self {
+            gui: gui_with(panel),
+        }
+    }
+}
+
+impl State for InventoryScreen {
+    fn handle_event(&mut self, game: &mut Game, event: &Event) -> Transition {
+        let world = match &mut game.world {
+            Some(w) => w,
+            None => return Transition::Pop,
+        };
+        if !self.gui.handle_event(event)
+            && (escape(event) || game.input.pressed(event).contains(&Action::OpenInventory))
+        {
+            return Transition::Pop;
+        }
+        for e in gui_events(&mut self.gui, &mut game.audio) {
+            if let GuiEvent::Selected(_, i) = e {
+                let inventory = &mut world.player.inventory;
+                if i < HOTBAR_SIZE {
+                    inventory.selected = i;
+                } else {
+                    inventory.slots.swap(i, inventory.selected);
+                }
+            }
+        }
+        let lines = slot_lines(world);
+        if let Some(WidgetKind::ScrollList { items, .. }) =
+            self.gui.get_mut("slots").map(|w| &mut w.kind)
+        {
+            *items = lines;
+        }
+        Transition::None
+    }
+
+    fn render(&mut self, _game: &mut Game, canvas: &mut dyn RenderBackend, font: &Font) {
+        draw_menu(&mut self.gui, canvas, font, true);
+    }
+
+    fn overlay(&self) -> bool {
+        true
+    }
+}
+
+//Shown over the world when the player runs out of health
+pub struct Dead {
+    gui: Gui,
+}
+
+impl Dead {
+    pub fn new() -> Self {
+        let mut gui = gui_with(menu(
+            "You died",
+            &[("respawn", "Respawn"), ("quit_menu", "Quit to menu")],
+        ));
+        if let Some(WidgetKind::Label { color, .. }) = gui.get_mut("title").map(|w| &mut w.kind) {
+            *color = Color::RGB(230, 70, 70);
+        }
+        Self { gui: gui }
+    }
+}
+
+impl State for Dead {
+    fn handle_event(&mut self, game: &mut Game, event: &Event) -> Transition {
+        self.gui.handle_event(event);
+        for e in gui_events(&mut self.gui, &mut game.audio) {
+            if let GuiEvent::Clicked(id) = e {
+                match id.as_str() {
+                    "respawn" => {
+                        if let Some(world) = &mut game.world {
+                            let player = &mut world.player;
+                            player.health.hp = player.health.max;
+                            player.health.knockback = (0.0, 0.0);
+                            player.pos = Player::new().pos;
+                            game.render.camera.snap(player.pos);
+                        }
+                        return Transition::Pop;
+                    }
+                    "quit_menu" => {
+                        game.close_world();
+                        return Transition::Switch(Box::new(MainMenu::new()));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Transition::None
+    }
+
+    fn render(&mut self, _game: &mut Game, canvas: &mut dyn RenderBackend, font: &Font) {
+        draw_menu(&mut self.gui, canvas, font, true);
+    }
+
+    fn overlay(&self) -> bool {
+        true
+    }
+}
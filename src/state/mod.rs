@@ -0,0 +1,3 @@
+pub mod menus;
+pub mod playing;
+pub mod state;
@@ -0,0 +1,194 @@
+use sdl2::{event::Event, keyboard::Keycode, ttf::Font};
+use std::collections::HashSet;
+
+use crate::event::event::{GameEvent, Target};
+use crate::input::input::Action;
+use crate::item::item::HOTBAR_SIZE;
+use crate::renderer::{backend::RenderBackend, map::player_tile, renderer::RenderMode};
+use crate::state::menus::{Dead, InventoryScreen, Paused};
+use crate::state::state::{Game, State, Transition};
+use crate::world::world::{World, REACH};
+
+// Tile the controller cursor is on: where the right stick points (up to the player's reach),
+// or the tile in front of the player when the stick is centered
+fn pad_target(world: &World, aim: (f64, f64), tile_size: i32) -> (i32, i32) {
+    let p = player_tile(world, tile_size);
+    let offset = if aim == (0.0, 0.0) {
+        world.player.facing
+    } else {
+        (aim.0 * REACH, aim.1 * REACH)
+    };
+    (
+        (p.0 + offset.0).floor() as i32,
+        (p.1 + offset.1).floor() as i32,
+    )
+}
+
+//The world running, with the HUD over it
+pub struct Playing;
+
+impl State for Playing {
+    fn handle_event(&mut self, game: &mut Game, event: &Event) -> Transition {
+        let render = &mut game.render;
+        let world = match &mut game.world {
+            Some(w) => w,
+            None => return Transition::Pop,
+        };
+        let actions = game.input.pressed(event);
+        // the console key closes the console as well, so it goes before the gui
+        if actions.contains(&Action::Console) {
+            render.gui.console.toggle();
+            return Transition::None;
+        }
+        // widgets get input first, the game only sees what they didn't use
+        if render.gui.handle_event(event) {
+            return Transition::None;
+        }
+        for action in actions {
+            match action {
+                Action::Pause if render.map.open => render.map.open = false,
+                Action::Pause => return Transition::Push(Box::new(Paused::new())),
+                Action::OpenInventory => {
+                    return Transition::Push(Box::new(InventoryScreen::new(world)))
+                }
+                Action::Map => render.map.toggle(player_tile(world, render.tile_size)),
+                Action::HotbarPrev | Action::HotbarNext => {
+                    let inventory = &mut world.player.inventory;
+                    inventory.selected = if action == Action::HotbarNext {
+                        (inventory.selected + 1) % HOTBAR_SIZE
+                    } else {
+                        (inventory.selected + HOTBAR_SIZE - 1) % HOTBAR_SIZE
+                    };
+                }
+                // clicks on the open map drag it instead
+                Action::Break | Action::Interact if !render.map.open => {
+                    let tile = match render.target {
+                        Some(tile) if game.input.using_pad => tile,
+                        _ => render
+                            .camera
+                            .screen_to_tile(render.gui.mouse(), render.tile_size),
+                    };
+                    if action == Action::Break {
+                        world.break_tile(tile, render.tile_size);
+                    } else {
+                        world.place_tile(tile, render.tile_size);
+                    }
+                }
+                _ => {}
+            }
+        }
+        match event {
+            Event::KeyDown {
+                keycode: Some(Keycode::F2),
+                repeat: false,
+                ..
+            } => {
+                render.mode = match render.mode {
+                    RenderMode::Sprites => RenderMode::Glyphs,
+                    RenderMode::Glyphs => RenderMode::Sprites,
+                };
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::F3),
+                repeat: false,
+                ..
+            } => {
+                render.gui.debug.open = !render.gui.debug.open;
+            }
+            Event::KeyDown {
+                keycode: Some(key @ (Keycode::G | Keycode::H | Keycode::K)),
+                repeat: false,
+                ..
+            } if render.gui.debug.open => {
+                let debug = &mut render.gui.debug;
+                match key {
+                    Keycode::G => debug.chunk_borders = !debug.chunk_borders,
+                    Keycode::H => debug.hitboxes = !debug.hitboxes,
+                    _ => debug.solid_tiles = !debug.solid_tiles,
+                }
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::F4),
+                repeat: false,
+                ..
+            } => {
+                world.vision.line_of_sight = !world.vision.line_of_sight;
+            }
+            Event::MouseWheel { y, .. } => {
+                if render.map.open {
+                    render.map.zoom_by(*y);
+                } else {
+                    render.camera.zoom_by(*y);
+                }
+            }
+            Event::MouseMotion {
+                mousestate,
+                xrel,
+                yrel,
+                ..
+            } if render.map.open && mousestate.left() => {
+                render.map.pan((*xrel, *yrel));
+            }
+            _ => {}
+        }
+        Transition::None
+    }
+
+    fn update(&mut self, game: &mut Game) -> Transition {
+        let render = &mut game.render;
+        let world = match &mut game.world {
+            Some(w) => w,
+            None => return Transition::Pop,
+        };
+        render.gui.console.run_pending(world, render.tile_size);
+        // keys typed into the console or a text input don't move the player
+        let actions = if render.gui.typing() {
+            HashSet::new()
+        } else {
+            game.input.held()
+        };
+        let stick = if render.gui.typing() {
+            (0.0, 0.0)
+        } else {
+            game.input.move_stick()
+        };
+        let from = world.player.pos;
+        world.player.input(
+            &actions,
+            stick,
+            &world.world,
+            &world.tiles,
+            render.tile_size,
+        );
+        world.step(from, render.tile_size);
+        world.update(render.tile_size);
+        render.target = if game.input.using_pad {
+            Some(pad_target(world, game.input.aim_stick(), render.tile_size))
+        } else {
+            None
+        };
+        render.camera.update(world.player.pos);
+
+        for event in world.events.iter() {
+            render.gui.debug.log(event);
+            if let GameEvent::Damaged {
+                target: Target::Player,
+                ..
+            } = event
+            {
+                render.camera.shake(6.0, 10);
+            }
+        }
+        if world.player.health.hp <= 0 {
+            return Transition::Push(Box::new(Dead::new()));
+        }
+        Transition::None
+    }
+
+    fn render(&mut self, game: &mut Game, canvas: &mut dyn RenderBackend, font: &Font) {
+        let mouse = game.render.gui.mouse();
+        if let Some(world) = &mut game.world {
+            game.render.render(canvas, world, font, mouse);
+        }
+    }
+}
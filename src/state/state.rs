@@ -0,0 +1,153 @@
+use sdl2::{event::Event, ttf::Font};
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::audio::audio::Audio;
+use crate::input::input::InputState;
+use crate::renderer::{backend::RenderBackend, renderer::Renderer};
+use crate::save::save::{load_or_generate, save_world};
+use crate::settings::settings::Settings;
+use crate::world::world::World;
+
+//Everything the states share
+pub struct Game {
+    pub world: Option<World>, //None in the menus before a world is opened
+    pub save_dir: PathBuf,    //Where the open world is saved
+    pub render: Renderer,
+    pub settings: Settings, //In use, with the command line options and F11 applied
+    pub saved_settings: Settings, //As in the settings file, the menus change both
+    pub input: InputState,
+    pub audio: Audio,
+    pub script: Option<PathBuf>, //Console commands run on every world that gets opened
+}
+
+impl Game {
+    pub fn new(
+        render: Renderer,
+        input: InputState,
+        audio: Audio,
+        settings: Settings,
+        saved_settings: Settings,
+    ) -> Self {
+        Self {
+            world: None,
+            save_dir: PathBuf::new(),
+            render: render,
+            settings: settings,
+            saved_settings: saved_settings,
+            input: input,
+            audio: audio,
+            script: None,
+        }
+    }
+
+    // Loads (or creates) the world saved in dir and makes it the open one
+    pub fn open_world(&mut self, dir: &Path) -> io::Result<()> {
+        let mut world = load_or_generate(dir, self.settings.seed, self.settings.gen_radius)?;
+        if let Some(script) = &self.script {
+            let console = &mut self.render.gui.console;
+            if let Err(e) = console.run_script(&mut world, script, self.render.tile_size) {
+                println!("Could not run {}: {}", script.display(), e);
+            }
+        }
+        self.render.camera.snap(world.player.pos);
+        self.render.map.clear();
+        self.world = Some(world);
+        self.save_dir = dir.to_path_buf();
+        Ok(())
+    }
+
+    // Saves the open world (if there is one), printing what went wrong instead of failing
+    pub fn save(&self) {
+        if let Some(world) = &self.world {
+            if let Err(e) = save_world(world, &self.save_dir, self.render.tile_size) {
+                println!("Could not save world: {}", e);
+            }
+        }
+    }
+
+    // Saves and closes the open world
+    pub fn close_world(&mut self) {
+        self.save();
+        self.world = None;
+    }
+}
+
+// What the state stack should do after a state handled something
+pub enum Transition {
+    None,
+    Push(Box<dyn State>),   //Put a state on top (menus, overlays)
+    Pop,                    //Go back to the state below
+    Switch(Box<dyn State>), //Replace the whole stack
+    Quit,
+}
+
+//One screen of the game, only the state on top gets input and updates
+pub trait State {
+    fn handle_event(&mut self, game: &mut Game, event: &Event) -> Transition;
+    // Runs one tick
+    fn update(&mut self, _game: &mut Game) -> Transition {
+        Transition::None
+    }
+    fn render(&mut self, game: &mut Game, canvas: &mut dyn RenderBackend, font: &Font);
+    // Overlays are drawn over the state below them (which stays frozen) instead of replacing it
+    fn overlay(&self) -> bool {
+        false
+    }
+}
+
+pub struct StateStack {
+    states: Vec<Box<dyn State>>,
+    pub running: bool,
+}
+
+impl StateStack {
+    pub fn new(first: Box<dyn State>) -> Self {
+        Self {
+            states: vec![first],
+            running: true,
+        }
+    }
+
+    fn apply(&mut self, transition: Transition) {
+        match transition {
+            Transition::None => {}
+            Transition::Push(state) => self.states.push(state),
+            Transition::Pop => {
+                self.states.pop();
+            }
+            Transition::Switch(state) => {
+                self.states.clear();
+                self.states.push(state);
+            }
+            Transition::Quit => self.running = false,
+        }
+        if self.states.is_empty() {
+            self.running = false;
+        }
+    }
+
+    pub fn handle_event(&mut self, game: &mut Game, event: &Event) {
+        if let Some(top) = self.states.last_mut() {
+            let transition = top.handle_event(game, event);
+            self.apply(transition);
+        }
+    }
+
+    pub fn update(&mut self, game: &mut Game) {
+        if let Some(top) = self.states.last_mut() {
+            let transition = top.update(game);
+            self.apply(transition);
+        }
+    }
+
+    // Draws the top state and every overlay below it down to the first full screen state
+    pub fn render(&mut self, game: &mut Game, canvas: &mut dyn RenderBackend, font: &Font) {
+        let first = self.states.iter().rposition(|s| !s.overlay()).unwrap_or(0);
+        for state in self.states[first..].iter_mut() {
+            state.render(game, canvas, font);
+        }
+    }
+}
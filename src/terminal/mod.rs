@@ -0,0 +1 @@
+pub mod terminal;
\ No newline at end of file
@@ -0,0 +1,172 @@
+use sdl2::pixels::Color;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    env,
+    io::{self, BufRead, Write},
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+
+use crate::input::input::Action;
+use crate::renderer::glyphs::tile_glyph;
+use crate::world::world::World;
+
+const STEP_TICKS: i32 = 8; //Ticks a typed key is held down for
+const RENDER_EVERY: u32 = 3; //Ticks between redraws (10 fps)
+
+// Asks the terminal for its size. When stdout isn't a terminal COLUMNS and LINES are used if set
+// (shells rarely export them to programs), 80x24 otherwise
+fn terminal_size() -> (i32, i32) {
+    #[cfg(unix)]
+    {
+        // SAFETY: TIOCGWINSZ only writes a winsize into the struct it is given
+        let mut ws: libc::winsize = unsafe { std::mem::zeroed() };
+        let ok = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut ws) } == 0;
+        if ok && ws.ws_col > 0 && ws.ws_row > 0 {
+            return (ws.ws_col as i32, ws.ws_row as i32);
+        }
+    }
+    let get = |name: &str, default: i32| {
+        env::var(name)
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(default)
+    };
+    (get("COLUMNS", 80), get("LINES", 24))
+}
+
+// Maps a typed character to the action it stands for
+fn action_for(c: char) -> Option<Action> {
+    match c {
+        'w' => Some(Action::MoveUp),
+        'a' => Some(Action::MoveLeft),
+        's' => Some(Action::MoveDown),
+        'd' => Some(Action::MoveRight),
+        ' ' | 'f' => Some(Action::Attack),
+        _ => None,
+    }
+}
+
+fn fg(c: Color) -> String {
+    format!("\x1b[38;2;{};{};{}m", c.r, c.g, c.b)
+}
+
+fn bg(c: Color) -> String {
+    format!("\x1b[48;2;{};{};{}m", c.r, c.g, c.b)
+}
+
+// Draws the view around the player with ANSI colors, one tile per character
+fn draw(world: &mut World, tile_size: i32, size: (i32, i32)) {
+    let (cols, rows) = (size.0, size.1 - 1); // last line is the status bar
+    let p = world.player.pos;
+    let pt = (
+        (p.0 / tile_size as f64).floor() as i32,
+        (p.1 / tile_size as f64).floor() as i32,
+    );
+    let x = (pt.0 - cols / 2, pt.0 - cols / 2 + cols);
+    let y = (pt.1 - rows / 2, pt.1 - rows / 2 + rows);
+    world.gen(x, y);
+
+    // characters of entities and the player on top of the tiles
+    let mut actors = HashMap::new();
+    for id in world.entities.alive.iter() {
+        let pos = world.entities.positions.get(id).unwrap();
+        let sprite = world.entities.sprites.get(id).unwrap();
+        let t = (
+            (pos.0 / tile_size as f64).floor() as i32,
+            (pos.1 / tile_size as f64).floor() as i32,
+        );
+        actors.insert(t, (sprite.symbol, sprite.color));
+    }
+    actors.insert(pt, ('@', Color::WHITE));
+
+    let mut out = String::from("\x1b[H");
+    for i in y.0..y.1 {
+        let mut last: Option<(Color, Color)> = None;
+        for j in x.0..x.1 {
+            let t = world.tiles.get(world.world.get(&(j, i)).unwrap()).unwrap();
+            let (mut c, mut f, b) = tile_glyph(t);
+            if let Some((symbol, color)) = actors.get(&(j, i)) {
+                c = *symbol;
+                f = *color;
+            }
+            // only switch colors when they change
+            if last != Some((f, b)) {
+                out.push_str(&fg(f));
+                out.push_str(&bg(b));
+                last = Some((f, b));
+            }
+            out.push(c);
+        }
+        out.push_str("\x1b[0m\n");
+    }
+    out.push_str(&format!(
+        "\x1b[0m\x1b[KHP {}/{}  wasd: move (one step per letter), f: attack, q: quit, then enter",
+        world.player.health.hp, world.player.health.max
+    ));
+
+    let mut stdout = io::stdout().lock();
+    let _ = stdout.write_all(out.as_bytes());
+    let _ = stdout.flush();
+}
+
+// Runs the game in the terminal without SDL, for headless sessions (e.g. over SSH)
+// Terminals only send input line by line, so every typed letter holds its key for a few ticks
+pub fn run_terminal(world: &mut World, tile_size: i32) {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            match line {
+                Ok(l) => {
+                    if tx.send(l).is_err() {
+                        return;
+                    }
+                }
+                Err(_) => break,
+            }
+        }
+        let _ = tx.send("q".to_string()); // end of input quits
+    });
+
+    print!("\x1b[2J\x1b[?25l"); // clear the screen and hide the cursor
+    let mut queue: VecDeque<Action> = VecDeque::new();
+    let mut held = 0;
+    let mut tick: u32 = 0;
+    let mut running = true;
+
+    while running {
+        while let Ok(line) = rx.try_recv() {
+            for c in line.chars() {
+                if c == 'q' {
+                    running = false;
+                }
+                if let Some(action) = action_for(c) {
+                    queue.push_back(action);
+                }
+            }
+        }
+
+        let mut actions: HashSet<Action> = HashSet::new();
+        if let Some(action) = queue.front() {
+            actions.insert(*action);
+            held += 1;
+            if held >= STEP_TICKS {
+                queue.pop_front();
+                held = 0;
+            }
+        }
+
+        world
+            .player
+            .input(&actions, (0.0, 0.0), &world.world, &world.tiles, tile_size);
+        world.update(tile_size);
+        if tick.is_multiple_of(RENDER_EVERY) {
+            draw(world, tile_size, terminal_size());
+        }
+        world.events.clear();
+        tick += 1;
+        thread::sleep(Duration::new(0, 1_000_000_000u32 / 30));
+    }
+    println!("\x1b[0m\x1b[?25h"); // reset colors and show the cursor again
+}
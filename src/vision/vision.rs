@@ -0,0 +1,137 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::world::world::{chunk_of, Tile, CHUNK_SIZE};
+
+pub const VIEW_RADIUS: i32 = 12; //How far the player can see (in tiles)
+
+// If a tile blocks the view of what is behind it (liquids are solid but can be seen across)
+fn opaque(t: &Tile) -> bool {
+    t.solid && !t.liquid
+}
+
+//Tiles the player has seen at least once, one bit per tile and one row of bits per chunk row
+pub struct Explored {
+    pub chunks: HashMap<(i32, i32), Vec<u32>>,
+}
+
+impl Explored {
+    pub fn new() -> Self {
+        Self {
+            chunks: HashMap::new(),
+        }
+    }
+
+    // Marks a tile as explored, returns true if it wasn't before
+    pub fn insert(&mut self, tile: (i32, i32)) -> bool {
+        let c = chunk_of(tile);
+        let rows = self
+            .chunks
+            .entry(c)
+            .or_insert_with(|| vec![0; CHUNK_SIZE as usize]);
+        let bit = 1 << (tile.0 - c.0 * CHUNK_SIZE);
+        let row = &mut rows[(tile.1 - c.1 * CHUNK_SIZE) as usize];
+        let new = *row & bit == 0;
+        *row |= bit;
+        new
+    }
+
+    pub fn contains(&self, tile: (i32, i32)) -> bool {
+        let c = chunk_of(tile);
+        match self.chunks.get(&c) {
+            Some(rows) => {
+                rows[(tile.1 - c.1 * CHUNK_SIZE) as usize] & (1 << (tile.0 - c.0 * CHUNK_SIZE)) != 0
+            }
+            None => false,
+        }
+    }
+}
+
+// Checks if nothing opaque is between two tiles (the tiles themselves don't count)
+fn line_of_sight(
+    world: &HashMap<(i32, i32), i32>,
+    tiles: &HashMap<i32, Tile>,
+    from: (i32, i32),
+    to: (i32, i32),
+) -> bool {
+    // bresenham line
+    let (dx, dy) = ((to.0 - from.0).abs(), -(to.1 - from.1).abs());
+    let (sx, sy) = ((to.0 - from.0).signum(), (to.1 - from.1).signum());
+    let mut err = dx + dy;
+    let mut p = from;
+    loop {
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            p.0 += sx;
+        }
+        if e2 <= dx {
+            err += dx;
+            p.1 += sy;
+        }
+        if p == to {
+            return true;
+        }
+        let blocked = world
+            .get(&p)
+            .and_then(|id| tiles.get(id))
+            .is_some_and(opaque);
+        if blocked {
+            return false;
+        }
+    }
+}
+
+//What the player currently sees, recomputed only when the player or a tile moved
+pub struct Vision {
+    pub line_of_sight: bool, //If opaque tiles block the view (otherwise everything in range is seen)
+    pub visible: HashSet<(i32, i32)>,
+    at: Option<((i32, i32), u32, bool)>, //Player tile, world revision and mode visible was built for
+}
+
+impl Vision {
+    pub fn new() -> Self {
+        Self {
+            line_of_sight: false,
+            visible: HashSet::new(),
+            at: None,
+        }
+    }
+
+    // Rebuilds the visible tiles if needed and adds them to explored, returns the chunks that got
+    // newly explored tiles
+    pub fn update(
+        &mut self,
+        world: &HashMap<(i32, i32), i32>,
+        tiles: &HashMap<i32, Tile>,
+        revision: u32,
+        player: (i32, i32),
+        explored: &mut Explored,
+    ) -> HashSet<(i32, i32)> {
+        let mut changed = HashSet::new();
+        if self.at == Some((player, revision, self.line_of_sight)) {
+            return changed;
+        }
+        self.at = Some((player, revision, self.line_of_sight));
+
+        self.visible.clear();
+        for y in -VIEW_RADIUS..=VIEW_RADIUS {
+            for x in -VIEW_RADIUS..=VIEW_RADIUS {
+                if x * x + y * y > VIEW_RADIUS * VIEW_RADIUS {
+                    continue;
+                }
+                let tile = (player.0 + x, player.1 + y);
+                if !world.contains_key(&tile) {
+                    continue;
+                }
+                if self.line_of_sight && !line_of_sight(world, tiles, player, tile) {
+                    continue;
+                }
+                self.visible.insert(tile);
+                if explored.insert(tile) {
+                    changed.insert(chunk_of(tile));
+                }
+            }
+        }
+        changed
+    }
+}
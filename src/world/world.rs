@@ -1,17 +1,51 @@
 use noise::{NoiseFn, Perlin};
 use rand::{rngs::ThreadRng, Rng};
 use sdl2::{pixels::Color, render};
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
 
+use crate::combat::combat::{
+    damage_system, entity_attack_system, knockback_system, player_attack_system,
+};
+use crate::entity::entity::{ai_system, movement_system, path_system, Entities, Entity, Sprite};
+use crate::event::event::GameEvent;
+use crate::item::item::{item_system, load_items, DroppedItem, ItemDef, ItemStack};
+use crate::light::light::{ambient_light, LightMap};
+use crate::mob::mob::{despawn_system, load_mobs, spawn_system, MobDef};
+use crate::pathfinding::pathfinding::PathCache;
+use crate::vision::vision::Vision;
 use crate::{player::player::Player, renderer::renderer::Renderer};
 
+pub const CHUNK_SIZE: i32 = 16; //Width and height of a chunk in tiles
+pub const REACH: f64 = 2.5; //How far the player can break and place tiles (in tiles)
+pub const ITEM_SIZE: (u32, u32) = (12, 12); //Hitbox of dropped items
+pub const DAY_LENGTH: u32 = 30 * 60 * 4; //Ticks in a full day (4 minutes at 30fps)
+pub const STEP_LENGTH: f64 = 0.8; //Tiles the player walks between footsteps
+
+// Gets the chunk a tile belongs to
+pub fn chunk_of(tile: (i32, i32)) -> (i32, i32) {
+    (
+        tile.0.div_euclid(CHUNK_SIZE),
+        tile.1.div_euclid(CHUNK_SIZE),
+    )
+}
+
 //One world tile
 pub struct Tile {
     pub name: String,
     pub id: i32,
     pub color: Color, //Color of the tile
     pub symbol: char, //Text symbol drawn on tile (decoration)
+    pub sprite: Option<String>,      //Name of the tile's frame in the tile atlas
     pub solid: bool,  //If entities can walk through the tile
+    pub liquid: bool, //If swimming entities can move through the tile
+    pub breakable: bool, //If the player can break the tile (leaving the terrain underneath)
+    pub drop: Option<(String, u32)>, //Item and amount dropped when broken
+    pub edges: Vec<i32>, //Tiles this tile spills its edges over where they meet (autotiling)
+    pub light: u8,       //Light level the tile gives off (0 - 15)
+    pub material: Option<String>, //What the tile is made of, picks its footstep and break sounds
 }
 
 //Holds information about the world
@@ -20,8 +54,21 @@ pub struct World {
     pub tiles: HashMap<i32, Tile>,       //Stores all tiles based on id (0..n)
     pub rng: ThreadRng,
     pub player: Player,
+    pub entities: Entities, //Every non-player actor
     pub noise: Perlin,
+    pub seed: u32, //Seed of the terrain noise
     pub tree_gap: HashMap<i32, i32>, //Gaps based on biome (tile id)
+    pub mobs: Vec<MobDef>,           //Creatures that can spawn, loaded from data
+    pub time: u32,                   //Ticks since the world was created
+    pub revision: u32,               //Bumped every time a tile changes
+    pub edits: u32,                  //Bumped when a tile that was already there is replaced
+    pub changed_chunks: HashSet<(i32, i32)>, //Chunks with tiles changed since the map redrew them
+    pub changed_tiles: Vec<(i32, i32)>, //Tiles changed since the light was updated
+    pub paths: PathCache,            //Paths found since the last edit
+    pub events: Vec<GameEvent>,      //Events from the current frame
+    pub items: HashMap<String, ItemDef>, //Item definitions by name
+    pub light: LightMap,                 //Light from light sources
+    pub vision: Vision,                  //Tiles the player can see right now
     scale: f64,
 }
 
@@ -41,7 +88,14 @@ fn init_tiles(tiles: &mut HashMap<i32, Tile>) {
             id: -1,
             color: Color::RGB(0, 0, 0),
             symbol: ' ',
+            sprite: None,
             solid: false,
+            liquid: false,
+            breakable: false,
+            drop: None,
+            edges: Vec::new(),
+            light: 0,
+            material: None,
         },
     );
     tiles.insert(
@@ -51,7 +105,14 @@ fn init_tiles(tiles: &mut HashMap<i32, Tile>) {
             id: 0,
             color: Color::RGB(0, 255, 0),
             symbol: ',',
+            sprite: Some("grass".to_string()),
             solid: false,
+            liquid: false,
+            breakable: false,
+            drop: None,
+            edges: vec![1], //grass fades into dirt
+            light: 0,
+            material: Some("grass".to_string()),
         },
     );
     tiles.insert(
@@ -61,7 +122,14 @@ fn init_tiles(tiles: &mut HashMap<i32, Tile>) {
             id: 1,
             color: Color::RGB(150, 75, 0),
             symbol: '.',
+            sprite: Some("dirt".to_string()),
             solid: false,
+            liquid: false,
+            breakable: false,
+            drop: None,
+            edges: Vec::new(),
+            light: 0,
+            material: Some("dirt".to_string()),
         },
     );
     tiles.insert(
@@ -70,8 +138,15 @@ fn init_tiles(tiles: &mut HashMap<i32, Tile>) {
             name: "Water".to_string(),
             id: 2,
             color: Color::RGB(0, 0, 255),
-            symbol: ' ',
+            symbol: '~',
+            sprite: Some("water".to_string()),
             solid: true,
+            liquid: true,
+            breakable: false,
+            drop: None,
+            edges: Vec::new(),
+            light: 0,
+            material: Some("water".to_string()),
         },
     );
     tiles.insert(
@@ -81,7 +156,14 @@ fn init_tiles(tiles: &mut HashMap<i32, Tile>) {
             id: 3,
             color: Color::RGB(255, 255, 0),
             symbol: '.',
+            sprite: Some("sand".to_string()),
             solid: false,
+            liquid: false,
+            breakable: false,
+            drop: None,
+            edges: vec![2], //sandy shorelines
+            light: 0,
+            material: Some("sand".to_string()),
         },
     );
     tiles.insert(
@@ -90,8 +172,15 @@ fn init_tiles(tiles: &mut HashMap<i32, Tile>) {
             name: "Snow".to_string(),
             id: 4,
             color: Color::RGB(255, 255, 255),
-            symbol: ' ',
+            symbol: '*',
+            sprite: Some("snow".to_string()),
             solid: false,
+            liquid: false,
+            breakable: false,
+            drop: None,
+            edges: Vec::new(),
+            light: 0,
+            material: Some("snow".to_string()),
         },
     );
     tiles.insert(
@@ -101,11 +190,126 @@ fn init_tiles(tiles: &mut HashMap<i32, Tile>) {
             id: 5,
             color: Color::RGB(248, 223, 161), //birch color
             symbol: 'T',
+            sprite: Some("tree".to_string()),
+            solid: true,
+            liquid: false,
+            breakable: true,
+            drop: Some(("Wood".to_string(), 2)),
+            edges: Vec::new(),
+            light: 0,
+            material: Some("wood".to_string()),
+        },
+    );
+    tiles.insert(
+        6,
+        Tile {
+            name: "Wood Wall".to_string(),
+            id: 6,
+            color: Color::RGB(120, 80, 40),
+            symbol: '#',
+            sprite: Some("wood_wall".to_string()),
+            solid: true,
+            liquid: false,
+            breakable: true,
+            drop: Some(("Wood".to_string(), 1)),
+            edges: Vec::new(),
+            light: 0,
+            material: Some("wood".to_string()),
+        },
+    );
+    tiles.insert(
+        7,
+        Tile {
+            name: "Torch".to_string(),
+            id: 7,
+            color: Color::RGB(255, 180, 60),
+            symbol: 'i',
+            sprite: None,
+            solid: false,
+            liquid: false,
+            breakable: true,
+            drop: Some(("Torch".to_string(), 1)),
+            edges: Vec::new(),
+            light: 12,
+            material: Some("wood".to_string()),
+        },
+    );
+    tiles.insert(
+        8,
+        Tile {
+            name: "Campfire".to_string(),
+            id: 8,
+            color: Color::RGB(230, 90, 20),
+            symbol: '^',
+            sprite: None,
             solid: true,
+            liquid: false,
+            breakable: true,
+            drop: Some(("Campfire".to_string(), 1)),
+            edges: Vec::new(),
+            light: 15,
+            material: Some("wood".to_string()),
         },
     );
 }
 
+// Checks if a box centered on pos overlaps any solid tile (shared by the player and entities)
+pub fn collides(
+    pos: (f64, f64),
+    size: (u32, u32),
+    world: &HashMap<(i32, i32), i32>,
+    tiles: &HashMap<i32, Tile>,
+    tile_size: i32,
+) -> bool {
+    collides_with(pos, size, world, tiles, tile_size, |t| t.solid)
+}
+
+// Checks if a box centered on pos overlaps any tile that blocks it
+pub fn collides_with(
+    pos: (f64, f64),
+    size: (u32, u32),
+    world: &HashMap<(i32, i32), i32>,
+    tiles: &HashMap<i32, Tile>,
+    tile_size: i32,
+    blocked: impl Fn(&Tile) -> bool,
+) -> bool {
+    let left: f64 = pos.0 - (size.0 as f64 / 2.0);
+    let right: f64 = pos.0 + (size.0 as f64 / 2.0);
+    let up: f64 = pos.1 - (size.1 as f64 / 2.0);
+    let down: f64 = pos.1 + (size.1 as f64 / 2.0);
+
+    // range of tiles covered by the box
+    let x = (
+        (left / tile_size as f64).floor() as i32,
+        (right / tile_size as f64).floor() as i32,
+    );
+    let y = (
+        (up / tile_size as f64).floor() as i32,
+        (down / tile_size as f64).floor() as i32,
+    );
+
+    for i in y.0..=y.1 {
+        for j in x.0..=x.1 {
+            let tid: i32 = *world.get(&(j, i)).unwrap_or(&-1);
+            let t: &Tile = tiles.get(&tid).unwrap();
+            if !blocked(t) {
+                continue;
+            }
+
+            let t_left: f64 = (j * tile_size) as f64;
+            let t_right: f64 = ((j + 1) * tile_size) as f64;
+            let t_up: f64 = (i * tile_size) as f64;
+            let t_down: f64 = ((i + 1) * tile_size) as f64;
+
+            if down <= t_up || up >= t_down || right <= t_left || left >= t_right {
+                continue;
+            }
+            return true;
+        }
+    }
+    false
+}
+
 impl World {
     pub fn new(player: Player) -> Self {
         let mut tiles = HashMap::new();
@@ -119,56 +323,123 @@ impl World {
             tiles: tiles,
             rng: rand::thread_rng(),
             player: player,
+            entities: Entities::new(),
             noise: Perlin::new(1),
+            seed: 1,
             tree_gap: tree_gap,
+            mobs: load_mobs(Path::new("assets/data/mobs.toml")),
+            time: DAY_LENGTH * 3 / 10, //Start in the morning
+            revision: 0,
+            edits: 0,
+            changed_chunks: HashSet::new(),
+            changed_tiles: Vec::new(),
+            paths: PathCache::new(),
+            events: Vec::new(),
+            items: load_items(Path::new("assets/data/items.toml")),
+            light: LightMap::new(),
+            vision: Vision::new(),
             scale: 0.05,
         };
     }
 
-    // Creates noise from x and y coords
-    fn get_noise(&self, x: f64, y: f64) -> f64 {
-        let val: f64 = self.noise.get([x + 0.01, y + 0.01]) / 2.0 + 0.5;
-        return val;
-    }
+    // Runs one simulation tick for everything in the world except the player
+    pub fn update(&mut self, tile_size: i32) {
+        self.time += 1;
+        spawn_system(self, tile_size);
+        despawn_system(self, tile_size);
 
-    // Gets absolute world coords from relative coords (on screen) using player position
-    pub fn get_abs_from_rel(&self, rel_pos: (i32, i32), renderer: &Renderer) -> (i32, i32) {
-        let hx = (renderer.screen_area.w as i32 / renderer.tile_size) / 2;
-        let hy = (renderer.screen_area.h as i32 / renderer.tile_size) / 2;
+        let mut hits = Vec::new();
+        player_attack_system(self, &mut hits);
+        let attackers = ai_system(&mut self.entities, self.player.pos, &mut self.rng);
+        entity_attack_system(self, attackers, &mut hits);
+        damage_system(self, hits);
+        knockback_system(self, tile_size);
+        item_system(self);
 
-        let p = self.player.pos;
+        path_system(
+            &mut self.entities,
+            &self.world,
+            &self.tiles,
+            self.edits,
+            &mut self.paths,
+            self.player.pos,
+            tile_size,
+        );
+        movement_system(&mut self.entities, &self.world, &self.tiles, tile_size);
 
-        // player position translated to tiles and rounded (what tile the player is currently on)
-        let pt: (i32, i32) = (
-            (p.0 as f64 / renderer.tile_size as f64).floor() as i32,
-            (p.1 as f64 / renderer.tile_size as f64).floor() as i32,
+        let p = self.player.pos;
+        let player_tile = (
+            (p.0 / tile_size as f64).floor() as i32,
+            (p.1 / tile_size as f64).floor() as i32,
+        );
+        self.light.update(
+            &self.world,
+            &self.tiles,
+            &self.changed_tiles,
+            self.revision,
+            player_tile,
+            self.player.lantern,
         );
+        self.changed_tiles.clear();
+        let explored = self.vision.update(
+            &self.world,
+            &self.tiles,
+            self.revision,
+            player_tile,
+            &mut self.player.explored,
+        );
+        self.changed_chunks.extend(explored);
+    }
 
-        // player relative position on the current player tile
-        let prt: (f64, f64) = (
-            ((pt.0 * renderer.tile_size) as f64 - p.0 as f64),
-            ((pt.1 * renderer.tile_size) as f64 - p.1 as f64),
+    // Adds up how far the player walked since from, with a footstep every STEP_LENGTH tiles
+    pub fn step(&mut self, from: (f64, f64), tile_size: i32) {
+        let p = self.player.pos;
+        self.player.stride += (p.0 - from.0).hypot(p.1 - from.1);
+        if self.player.stride < STEP_LENGTH * tile_size as f64 {
+            return;
+        }
+        self.player.stride = 0.0;
+        let tile = (
+            (p.0 / tile_size as f64).floor() as i32,
+            (p.1 / tile_size as f64).floor() as i32,
         );
+        if let Some(id) = self.world.get(&tile) {
+            self.events.push(GameEvent::Footstep { id: *id });
+        }
+    }
+
+    // Checks if the player can see a tile: explored tiles are remembered, but with line of sight
+    // on only the ones in view count
+    pub fn can_see(&self, tile: (i32, i32)) -> bool {
+        if self.vision.line_of_sight {
+            self.vision.visible.contains(&tile)
+        } else {
+            self.player.explored.contains(tile)
+        }
+    }
 
-        // screen offset when rendering tiles
-        let so: (i32, i32) = ((prt.0.floor() as i32), (prt.1.floor() as i32));
+    // Light level (0 - 15) at a tile, the brighter of daylight and nearby light sources
+    pub fn light_level(&self, tile: (i32, i32)) -> u8 {
+        ambient_light(self.time).max(self.light.get(tile))
+    }
 
-        // relative tile position
-        let rt = (
-            (rel_pos.0 - so.0) / renderer.tile_size,
-            (rel_pos.1 - so.1) / renderer.tile_size,
-        );
+    // Changes the terrain noise, only tiles generated from now on use the new seed
+    pub fn set_seed(&mut self, seed: u32) {
+        self.seed = seed;
+        self.noise = Perlin::new(seed);
+    }
 
-        // get absolute world coords
-        return (pt.0 + rt.0 - hx, pt.1 + rt.1 - hy);
+    // Creates noise from x and y coords
+    fn get_noise(&self, x: f64, y: f64) -> f64 {
+        let val: f64 = self.noise.get([x + 0.01, y + 0.01]) / 2.0 + 0.5;
+        return val;
     }
 
-    // Gets tile id from relative position
-    pub fn get_tile_id_from_rel(&self, rel_pos: (i32, i32), renderer: &Renderer) -> i32 {
-        let coords = self.get_abs_from_rel(rel_pos, &renderer);
-        let tile_id = self.world.get(&coords).unwrap();
-        let tile = self.tiles.get(&tile_id).unwrap();
-        return tile.id;
+    // Gets tile id from relative position, None if the tile there isn't generated (the mouse
+    // can be over the black bars around a fixed resolution, or off the shrunk window)
+    pub fn get_tile_id_from_rel(&self, rel_pos: (i32, i32), renderer: &Renderer) -> Option<i32> {
+        let coords = renderer.camera.screen_to_tile(rel_pos, renderer.tile_size);
+        self.world.get(&coords).copied()
     }
 
     // Gets tile id based off elevation (created by noise)
@@ -185,14 +456,22 @@ impl World {
         return 4; // Snow
     }
 
-    //Uses Perlin noise to generate tile and insert into map (careful, this may override already existing tiles!)
-    pub fn generate_tile(&mut self, x: i32, y: i32) {
+    // Gets the elevation (0 - 1) at a tile from the noise, it decides the terrain
+    pub fn elevation(&self, x: i32, y: i32) -> f64 {
         let fx = x as f64 * self.scale;
         let fy = y as f64 * self.scale;
-        let value =
-            (self.get_noise(fx, fy) + 0.5 * self.get_noise(fx, fy) + 0.25 * self.get_noise(fx, fy))
-                / (1.0 + 0.5 + 0.25);
-        let n_tile = self.get_tile(value);
+        (self.get_noise(fx, fy) + 0.5 * self.get_noise(fx, fy) + 0.25 * self.get_noise(fx, fy))
+            / (1.0 + 0.5 + 0.25)
+    }
+
+    // Gets the terrain (without trees) at a tile from the noise
+    pub fn terrain(&self, x: i32, y: i32) -> i32 {
+        self.get_tile(self.elevation(x, y))
+    }
+
+    //Uses Perlin noise to generate tile and insert into map (careful, this may override already existing tiles!)
+    pub fn generate_tile(&mut self, x: i32, y: i32) {
+        let n_tile = self.terrain(x, y);
         let tree_gap = *self.tree_gap.get(&n_tile).unwrap();
 
         //Check for tree generation
@@ -208,27 +487,159 @@ impl World {
             }
         }
 
-        if tree_gap > 0 && self.get_noise(50.0 * x as f64, 50.0 * y as f64) == max {
-            self.world.insert((x, y), 5);
+        let id = if tree_gap > 0 && self.get_noise(50.0 * x as f64, 50.0 * y as f64) == max {
+            5
         } else {
-            self.world.insert((x, y), self.get_tile(value));
+            n_tile
+        };
+        // regenerating can change tiles that were already there
+        if self.world.insert((x, y), id).is_some_and(|old| old != id) {
+            self.edits += 1;
         }
+        self.revision += 1;
+        self.changed_chunks.insert(chunk_of((x, y)));
+        self.changed_tiles.push((x, y));
     }
 
-    //Generates a random tile id from the tilemap
-    pub fn get_random_tile_id(&mut self) -> i32 {
-        let id: i32 = self.rng.gen_range(0..(self.tiles.len() as i32 - 1));
-        return id;
+    // Replaces a tile, use this instead of inserting into world directly so caches get updated
+    pub fn set_tile(&mut self, pos: (i32, i32), id: i32) {
+        self.world.insert(pos, id);
+        self.revision += 1;
+        self.edits += 1;
+        self.changed_chunks.insert(chunk_of(pos));
+        self.changed_tiles.push(pos);
     }
 
-    //Generates a uniformly random 21x21 square of tiles (from coords -10 to 10 in both directions)
-    pub fn random_load_debug(&mut self, x: (i32, i32), y: (i32, i32)) {
-        for i in x.0..x.1 {
-            for j in y.0..y.1 {
-                let id = self.get_random_tile_id();
-                self.world.insert((i, j), id);
+    // Checks if a tile is close enough to the player to be broken or placed
+    fn in_reach(&self, tile: (i32, i32), tile_size: i32) -> bool {
+        let center = (
+            (tile.0 as f64 + 0.5) * tile_size as f64,
+            (tile.1 as f64 + 0.5) * tile_size as f64,
+        );
+        let p = self.player.pos;
+        (center.0 - p.0).hypot(center.1 - p.1) <= REACH * tile_size as f64
+    }
+
+    // Breaks a tile next to the player, dropping its item and leaving the terrain underneath
+    pub fn break_tile(&mut self, tile: (i32, i32), tile_size: i32) {
+        if !self.in_reach(tile, tile_size) {
+            return;
+        }
+        let id = match self.world.get(&tile) {
+            Some(id) => *id,
+            None => return,
+        };
+        let t = self.tiles.get(&id).unwrap();
+        if !t.breakable {
+            return;
+        }
+        let drop = t.drop.clone();
+
+        let terrain = self.terrain(tile.0, tile.1);
+        self.set_tile(tile, terrain);
+        self.events.push(GameEvent::TileBroken { id: id });
+        if let Some((item, count)) = drop {
+            let center = (
+                (tile.0 as f64 + 0.5) * tile_size as f64,
+                (tile.1 as f64 + 0.5) * tile_size as f64,
+            );
+            self.drop_item(
+                center,
+                ItemStack {
+                    item: item,
+                    count: count,
+                },
+            );
+        }
+    }
+
+    // Places the tile of the selected item next to the player, if nothing is in the way
+    pub fn place_tile(&mut self, tile: (i32, i32), tile_size: i32) {
+        if !self.in_reach(tile, tile_size) {
+            return;
+        }
+        let stack = match &self.player.inventory.slots[self.player.inventory.selected] {
+            Some(s) => s,
+            None => return,
+        };
+        let id = match self.items.get(&stack.item).and_then(|def| def.places) {
+            Some(id) => id,
+            None => return,
+        };
+        match self.world.get(&tile) {
+            Some(current) => {
+                let t = self.tiles.get(current).unwrap();
+                if t.solid || t.liquid {
+                    return;
+                }
+            }
+            None => return,
+        }
+
+        // the new tile can't overlap the player or any entity
+        let mut world = HashMap::new();
+        world.insert(tile, id);
+        if collides(
+            self.player.pos,
+            self.player.size,
+            &world,
+            &self.tiles,
+            tile_size,
+        ) {
+            return;
+        }
+        for e in self.entities.alive.iter() {
+            let pos = *self.entities.positions.get(e).unwrap();
+            let size = *self.entities.hitboxes.get(e).unwrap();
+            if collides(pos, size, &world, &self.tiles, tile_size) {
+                return;
             }
         }
+
+        self.player.inventory.take_selected();
+        self.set_tile(tile, id);
+        self.events.push(GameEvent::TilePlaced { id: id });
+    }
+
+    // Drops an item on the ground, scattering it a little so drops don't all land on the same spot
+    pub fn drop_item(&mut self, pos: (f64, f64), stack: ItemStack) {
+        let pos = (
+            pos.0 + self.rng.gen_range(-8.0..8.0),
+            pos.1 + self.rng.gen_range(-8.0..8.0),
+        );
+        self.events.push(GameEvent::Dropped {
+            pos: pos,
+            item: stack.item.clone(),
+            count: stack.count,
+        });
+        self.spawn_item(pos, stack, 0);
+    }
+
+    // Spawns an item entity lying on the ground
+    pub fn spawn_item(&mut self, pos: (f64, f64), stack: ItemStack, age: u32) -> Entity {
+        let (color, symbol) = match self.items.get(&stack.item) {
+            Some(def) => (
+                Color::RGB(def.color.0, def.color.1, def.color.2),
+                def.symbol,
+            ),
+            None => (Color::MAGENTA, '?'),
+        };
+        let id = self.entities.spawn(
+            pos,
+            ITEM_SIZE,
+            Sprite {
+                color: color,
+                symbol: symbol,
+            },
+        );
+        self.entities.items.insert(
+            id,
+            DroppedItem {
+                stack: stack,
+                age: age,
+            },
+        );
+        id
     }
 
     pub fn gen(&mut self, x: (i32, i32), y: (i32, i32)) {
@@ -241,18 +652,4 @@ impl World {
             }
         }
     }
-
-    pub fn print_debug(&self) {
-        println!("{}", self.world.get(&(0, 0)).unwrap().clone());
-        for i in -10..10 {
-            for j in -10..10 {
-                if self.world.contains_key(&(j, i)) {
-                    print!("{}", self.world.get(&(j, i)).unwrap().clone());
-                } else {
-                    print!("-");
-                }
-            }
-            println!();
-        }
-    }
 }